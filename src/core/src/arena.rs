@@ -0,0 +1,344 @@
+//! A module for the `FrameArena`, a bump allocator for data that only lives during a frame (draw
+//! lists, culling results, debug lines...). Allocating only moves a pointer forward and nothing is
+//! freed until `FrameArena::reset`, which makes the whole arena available again at once. Keep it
+//! as a `World` resource and reset it at the start of every frame.
+
+use std::cmp::max;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+use std::sync::Mutex;
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+struct Chunk {
+    memory: Vec<u8>,
+    used: usize,
+}
+
+impl Chunk {
+    fn new(size: usize) -> Self {
+        Chunk {
+            memory: vec![0; size],
+            used: 0,
+        }
+    }
+
+    fn bump(&mut self, size: usize, align: usize) -> Option<*mut u8> {
+        let base = self.memory.as_mut_ptr() as usize;
+        let start = (base + self.used + align - 1) & !(align - 1);
+        let offset = start - base;
+        let end = match offset.checked_add(size) {
+            Some(end) if end <= self.memory.len() => end,
+            _ => return None,
+        };
+
+        self.used = end;
+        Some(unsafe { self.memory.as_mut_ptr().add(offset) })
+    }
+}
+
+/// A bump allocator for values that are thrown away at the end of the frame. Values are
+/// allocated through a shared reference, so systems can use the arena while the world is being
+/// processed, and can only be of `Copy` types since they are never dropped. When a chunk is full a
+/// new one is allocated, and the next `reset` merges them so the following frames fit in a single
+/// chunk.
+/// # Example
+/// ```
+/// extern crate luck_core;
+/// extern crate luck_ecs;
+///
+/// use luck_core::FrameArena;
+/// use luck_ecs::WorldBuilder;
+///
+/// fn main() {
+///     let mut w = WorldBuilder::new().with_resource(FrameArena::new()).build();
+///
+///     for _ in 0..2 {
+///         w.get_resource_mut::<FrameArena>().unwrap().reset();
+///
+///         let arena = w.get_resource::<FrameArena>().unwrap();
+///         let mut visible = arena.vec();
+///         for index in 0..100u32 {
+///             if index % 3 == 0 {
+///                 visible.push(index);
+///             }
+///         }
+///         let visible = visible.into_slice();
+///         assert_eq!(visible.len(), 34);
+///         assert_eq!(visible[1], 3);
+///     }
+/// }
+/// ```
+pub struct FrameArena {
+    chunks: Mutex<Vec<Chunk>>,
+    chunk_size: usize,
+}
+
+impl FrameArena {
+    /// Returns an empty arena that allocates its memory in 64KiB chunks.
+    pub fn new() -> Self {
+        FrameArena::with_chunk_size(DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Returns an empty arena that allocates its memory in chunks of `chunk_size` bytes. Values
+    /// larger than a chunk get a chunk of their own.
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
+        FrameArena {
+            chunks: Mutex::new(Vec::new()),
+            chunk_size: max(chunk_size, 1),
+        }
+    }
+
+    fn alloc_raw(&self, size: usize, align: usize) -> *mut u8 {
+        if size == 0 {
+            return align as *mut u8;
+        }
+
+        let mut chunks = self.chunks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(allocation) = chunks.last_mut().and_then(|chunk| chunk.bump(size, align)) {
+            return allocation;
+        }
+
+        let chunk_size = max(self.chunk_size, size + align);
+        chunks.push(Chunk::new(chunk_size));
+        chunks.last_mut()
+              .and_then(|chunk| chunk.bump(size, align))
+              .expect("A new chunk is large enough for the allocation")
+    }
+
+    fn alloc_array<T: Copy>(&self, len: usize) -> *mut T {
+        let size = mem::size_of::<T>().checked_mul(len).expect("Allocation too large");
+        self.alloc_raw(size, mem::align_of::<T>()) as *mut T
+    }
+
+    /// Moves a value into the arena.
+    // Every call returns a different allocation, so the mutable references never alias.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: Copy>(&self, value: T) -> &mut T {
+        unsafe {
+            let allocation = self.alloc_array::<T>(1);
+            ptr::write(allocation, value);
+            &mut *allocation
+        }
+    }
+
+    /// Returns a slice of `len` copies of `value`.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice<T: Copy>(&self, len: usize, value: T) -> &mut [T] {
+        unsafe {
+            let allocation = self.alloc_array::<T>(len);
+            for index in 0..len {
+                ptr::write(allocation.add(index), value);
+            }
+            slice::from_raw_parts_mut(allocation, len)
+        }
+    }
+
+    /// Returns a copy of `values` in the arena.
+    #[allow(clippy::mut_from_ref)]
+    pub fn copy_slice<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        unsafe {
+            let allocation = self.alloc_array::<T>(values.len());
+            ptr::copy_nonoverlapping(values.as_ptr(), allocation, values.len());
+            slice::from_raw_parts_mut(allocation, values.len())
+        }
+    }
+
+    /// Returns an empty `ScratchVec` that grows inside the arena.
+    pub fn vec<'a, T: Copy>(&'a self) -> ScratchVec<'a, T> {
+        self.vec_with_capacity(0)
+    }
+
+    /// Returns an empty `ScratchVec` that can hold `capacity` values before it has to grow.
+    pub fn vec_with_capacity<'a, T: Copy>(&'a self, capacity: usize) -> ScratchVec<'a, T> {
+        ScratchVec {
+            arena: self,
+            data: self.alloc_array(capacity),
+            len: 0,
+            capacity: capacity,
+        }
+    }
+
+    /// Frees every allocation at once. Requires a mutable reference, so nothing allocated from
+    /// the arena can still be borrowed.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if chunks.len() > 1 {
+            let size = chunks.iter().map(|chunk| chunk.memory.len()).sum();
+            chunks.clear();
+            chunks.push(Chunk::new(size));
+        } else if let Some(chunk) = chunks.last_mut() {
+            chunk.used = 0;
+        }
+    }
+
+    /// Returns how many bytes the arena reserved from the heap.
+    pub fn capacity(&self) -> usize {
+        let chunks = self.chunks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        chunks.iter().map(|chunk| chunk.memory.len()).sum()
+    }
+}
+
+impl Default for FrameArena {
+    fn default() -> Self {
+        FrameArena::new()
+    }
+}
+
+/// A growable list of `Copy` values stored in a `FrameArena`. Growing copies the values to a new
+/// allocation twice as large, the old one is only reclaimed when the arena is reset.
+pub struct ScratchVec<'a, T: Copy + 'a> {
+    arena: &'a FrameArena,
+    data: *mut T,
+    len: usize,
+    capacity: usize,
+}
+
+impl<'a, T: Copy> ScratchVec<'a, T> {
+    fn grow(&mut self, additional: usize) {
+        let required = self.len.checked_add(additional).expect("Capacity overflow");
+        if required <= self.capacity {
+            return;
+        }
+
+        let capacity = max(required, max(self.capacity * 2, 4));
+        let data = self.arena.alloc_array::<T>(capacity);
+        unsafe {
+            ptr::copy_nonoverlapping(self.data, data, self.len);
+        }
+        self.data = data;
+        self.capacity = capacity;
+    }
+
+    /// Appends a value to the end of the list.
+    pub fn push(&mut self, value: T) {
+        self.grow(1);
+        unsafe {
+            ptr::write(self.data.add(self.len), value);
+        }
+        self.len += 1;
+    }
+
+    /// Removes the last value of the list and returns it, or None if the list is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { ptr::read(self.data.add(self.len)) })
+        }
+    }
+
+    /// Appends every value of `values` to the end of the list.
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        self.grow(values.len());
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr(), self.data.add(self.len), values.len());
+        }
+        self.len += values.len();
+    }
+
+    /// Removes every value of the list, keeping its capacity.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Returns how many values the list can hold before it has to grow.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Converts the list into a slice that lives as long as the arena borrow.
+    pub fn into_slice(self) -> &'a mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.data, self.len) }
+    }
+}
+
+impl<'a, T: Copy> Deref for ScratchVec<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.data, self.len) }
+    }
+}
+
+impl<'a, T: Copy> DerefMut for ScratchVec<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.data, self.len) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FrameArena;
+
+    #[test]
+    fn allocations() {
+        let arena = FrameArena::with_chunk_size(64);
+
+        let a = arena.alloc(1u8);
+        let b = arena.alloc(2u64);
+        let c = arena.alloc_slice(3, 7u16);
+        let d = arena.copy_slice(&[1.0f32, 2.0, 3.0]);
+        let empty = arena.copy_slice::<u32>(&[]);
+        *a += 1;
+        *b += 1;
+        c[1] = 8;
+
+        assert_eq!(*a, 2);
+        assert_eq!(*b, 3);
+        assert_eq!(b as *mut u64 as usize % 8, 0);
+        assert_eq!(c, &[7, 8, 7]);
+        assert_eq!(d, &[1.0, 2.0, 3.0]);
+        assert!(empty.is_empty());
+
+        // Values larger than a chunk get their own chunk.
+        let large = arena.alloc_slice(100, 5u32);
+        assert_eq!(large.len(), 100);
+        assert!(large.iter().all(|&value| value == 5));
+        assert!(arena.capacity() > 64 + 400);
+    }
+
+    #[test]
+    fn scratch_vec() {
+        let arena = FrameArena::with_chunk_size(16);
+
+        let mut values = arena.vec();
+        assert_eq!(values.pop(), None);
+        for value in 0..10u32 {
+            values.push(value);
+        }
+        values.extend_from_slice(&[10, 11]);
+        assert_eq!(values.len(), 12);
+        assert!(values.capacity() >= 12);
+        assert_eq!(values.pop(), Some(11));
+        values[0] = 100;
+        assert_eq!(values.iter().sum::<u32>(), 100 + (1..11).sum::<u32>());
+
+        let other = arena.vec_with_capacity::<u8>(2);
+        assert_eq!(other.capacity(), 2);
+
+        let values = values.into_slice();
+        assert_eq!(values[10], 10);
+    }
+
+    #[test]
+    fn reset() {
+        let mut arena = FrameArena::with_chunk_size(32);
+        for value in 0..40u64 {
+            arena.alloc(value);
+        }
+        let capacity = arena.capacity();
+        assert!(capacity >= 40 * 8);
+
+        // The chunks are merged, so the same frame fits in a single chunk afterwards.
+        arena.reset();
+        assert_eq!(arena.capacity(), capacity);
+        for value in 0..40u64 {
+            assert_eq!(*arena.alloc(value), value);
+        }
+        assert_eq!(arena.capacity(), capacity);
+    }
+}
@@ -0,0 +1,148 @@
+//! A module with small encoding helpers to shrink serialized data. Integers are written as
+//! varints (7 bits per byte, the high bit marks that another byte follows), signed integers are
+//! zigzag encoded first so small negative numbers stay small, and sequences can be delta encoded
+//! so slowly changing values (ids, positions in fixed point) take one or two bytes each.
+
+/// Appends `value` to `out` as a varint. Values below 128 take a single byte, a full `u64` takes
+/// 10 bytes.
+pub fn write_varint(value: u64, out: &mut Vec<u8>) {
+    let mut value = value;
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads a varint from the start of `input`. Returns the value and how many bytes were read, or
+/// None if the input ends in the middle of the varint or the varint doesn't fit in a `u64`.
+pub fn read_varint(input: &[u8]) -> Option<(u64, usize)> {
+    let mut result = 0u64;
+    let mut shift = 0;
+
+    for (index, byte) in input.iter().enumerate() {
+        if shift > 63 || (shift == 63 && *byte > 1) {
+            return None;
+        }
+
+        result |= ((*byte & 0x7f) as u64) << shift;
+        if *byte & 0x80 == 0 {
+            return Some((result, index + 1));
+        }
+        shift += 7;
+    }
+
+    None
+}
+
+/// Maps signed integers to unsigned ones so that numbers close to zero (positive or negative)
+/// have small encodings: 0, -1, 1, -2, 2... become 0, 1, 2, 3, 4...
+pub fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// The inverse of `zigzag_encode`.
+pub fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Appends the length of `values` followed by the difference between each value and the previous
+/// one (the first value is compared to zero), every number written as a zigzag varint.
+pub fn delta_encode(values: &[i64], out: &mut Vec<u8>) {
+    write_varint(values.len() as u64, out);
+
+    let mut previous = 0i64;
+    for value in values {
+        write_varint(zigzag_encode(value.wrapping_sub(previous)), out);
+        previous = *value;
+    }
+}
+
+/// Reads a sequence written by `delta_encode` from the start of `input`. Returns the values and
+/// how many bytes were read, or None if the input is malformed.
+pub fn delta_decode(input: &[u8]) -> Option<(Vec<i64>, usize)> {
+    let (count, mut read) = match read_varint(input) {
+        Some(result) => result,
+        None => return None,
+    };
+
+    // Every value takes at least one byte, this avoids huge allocations for corrupted counts.
+    if count > (input.len() - read) as u64 {
+        return None;
+    }
+
+    let mut values = Vec::with_capacity(count as usize);
+    let mut previous = 0i64;
+    for _ in 0..count {
+        let (delta, size) = match read_varint(&input[read..]) {
+            Some(result) => result,
+            None => return None,
+        };
+        read += size;
+        previous = previous.wrapping_add(zigzag_decode(delta));
+        values.push(previous);
+    }
+
+    Some((values, read))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_varint, read_varint, zigzag_encode, zigzag_decode, delta_encode,
+                delta_decode};
+
+    #[test]
+    fn varint() {
+        let samples = [0u64, 1, 127, 128, 300, 16_384, u32::max_value() as u64, u64::max_value()];
+        for sample in &samples {
+            let mut buffer = Vec::new();
+            write_varint(*sample, &mut buffer);
+            assert_eq!(read_varint(&buffer), Some((*sample, buffer.len())));
+        }
+
+        let mut buffer = Vec::new();
+        write_varint(127, &mut buffer);
+        assert_eq!(buffer, vec![0x7f]);
+        buffer.clear();
+        write_varint(300, &mut buffer);
+        assert_eq!(buffer, vec![0xac, 0x02]);
+
+        assert_eq!(read_varint(&[0x80]), None);
+        assert_eq!(read_varint(&[]), None);
+        assert_eq!(read_varint(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]),
+                   None);
+    }
+
+    #[test]
+    fn zigzag() {
+        assert_eq!(zigzag_encode(0), 0);
+        assert_eq!(zigzag_encode(-1), 1);
+        assert_eq!(zigzag_encode(1), 2);
+        assert_eq!(zigzag_encode(-2), 3);
+
+        let samples = [0i64, 1, -1, 1000, -1000, i64::max_value(), i64::min_value()];
+        for sample in &samples {
+            assert_eq!(zigzag_decode(zigzag_encode(*sample)), *sample);
+        }
+    }
+
+    #[test]
+    fn delta() {
+        let values = [1000i64, 1001, 1003, 1002, i64::min_value(), i64::max_value()];
+        let mut buffer = Vec::new();
+        delta_encode(&values, &mut buffer);
+        buffer.push(0xff);
+
+        let (decoded, read) = delta_decode(&buffer).unwrap();
+        assert_eq!(decoded, values.to_vec());
+        assert_eq!(read, buffer.len() - 1);
+
+        // 1 byte for the count, 2 bytes for 1000 and a single byte for each small delta.
+        buffer.clear();
+        delta_encode(&values[..4], &mut buffer);
+        assert_eq!(buffer.len(), 6);
+
+        assert_eq!(delta_decode(&[5, 0, 0]), None);
+        assert_eq!(delta_decode(&[]), None);
+    }
+}
@@ -0,0 +1,237 @@
+//! A module for the `InfluenceMap` type. An influence map is a grid laid over the XZ plane of the
+//! world where each cell holds a value, useful for AI heuristics like territory control or
+//! danger maps.
+
+use luck_math::Vector3;
+use std::cmp;
+
+/// A grid of values aligned to the world. Cells are square, the cell `(0, 0)` starts at `origin`
+/// and the grid grows towards positive X and Z. The Y component of positions is ignored.
+#[derive(Debug, Clone)]
+pub struct InfluenceMap {
+    origin: Vector3<f32>,
+    cell_size: f32,
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+
+impl InfluenceMap {
+    /// Constructs a new `InfluenceMap` with `width * height` cells, every cell starts at zero.
+    /// # Panics
+    /// Panics if `cell_size` is not positive.
+    pub fn new(origin: Vector3<f32>, cell_size: f32, width: usize, height: usize) -> Self {
+        assert!(cell_size > 0.0);
+
+        InfluenceMap {
+            origin: origin,
+            cell_size: cell_size,
+            width: width,
+            height: height,
+            values: vec![0.0; width * height],
+        }
+    }
+
+    /// Returns the number of cells in the X axis.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of cells in the Z axis.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the cell that contains the world position or None if the position is outside the
+    /// map.
+    pub fn cell_at(&self, position: Vector3<f32>) -> Option<(usize, usize)> {
+        let x = ((position.x - self.origin.x) / self.cell_size).floor();
+        let z = ((position.z - self.origin.z) / self.cell_size).floor();
+
+        if x < 0.0 || z < 0.0 || x >= self.width as f32 || z >= self.height as f32 {
+            None
+        } else {
+            Some((x as usize, z as usize))
+        }
+    }
+
+    /// Returns the world position of the center of a cell.
+    pub fn cell_center(&self, x: usize, z: usize) -> Vector3<f32> {
+        Vector3::new(self.origin.x + (x as f32 + 0.5) * self.cell_size,
+                     self.origin.y,
+                     self.origin.z + (z as f32 + 0.5) * self.cell_size)
+    }
+
+    /// Returns the value of a cell. Cells outside the map have a value of zero.
+    pub fn get(&self, x: usize, z: usize) -> f32 {
+        if x < self.width && z < self.height {
+            self.values[z * self.width + x]
+        } else {
+            0.0
+        }
+    }
+
+    /// Sets the value of a cell. Cells outside the map are ignored.
+    pub fn set(&mut self, x: usize, z: usize, value: f32) {
+        if x < self.width && z < self.height {
+            self.values[z * self.width + x] = value;
+        }
+    }
+
+    /// Adds `amount` to every cell whose center is within `radius` of the position. The amount
+    /// falls off linearly, cells at the position receive the full amount and cells at `radius`
+    /// receive nothing. A radius of zero only affects the cell containing the position.
+    pub fn deposit(&mut self, position: Vector3<f32>, amount: f32, radius: f32) {
+        if radius <= 0.0 {
+            if let Some((x, z)) = self.cell_at(position) {
+                self.values[z * self.width + x] += amount;
+            }
+            return;
+        }
+
+        let cells = (radius / self.cell_size).ceil() as isize;
+        let cx = ((position.x - self.origin.x) / self.cell_size).floor() as isize;
+        let cz = ((position.z - self.origin.z) / self.cell_size).floor() as isize;
+
+        for z in (cz - cells)..(cz + cells + 1) {
+            for x in (cx - cells)..(cx + cells + 1) {
+                if x < 0 || z < 0 || x >= self.width as isize || z >= self.height as isize {
+                    continue;
+                }
+
+                let center = self.cell_center(x as usize, z as usize);
+                let dx = center.x - position.x;
+                let dz = center.z - position.z;
+                let distance = (dx * dx + dz * dz).sqrt();
+
+                if distance < radius {
+                    let falloff = 1.0 - distance / radius;
+                    self.values[z as usize * self.width + x as usize] += amount * falloff;
+                }
+            }
+        }
+    }
+
+    /// Multiplies every cell by `factor`. Calling this every frame with a factor smaller than 1
+    /// makes old influence fade away.
+    pub fn decay(&mut self, factor: f32) {
+        for value in &mut self.values {
+            *value *= factor;
+        }
+    }
+
+    /// Averages every cell with its 8 neighbours, spreading the influence over the map. Cells
+    /// outside the map don't take part in the average.
+    pub fn blur(&mut self) {
+        let mut result = Vec::with_capacity(self.values.len());
+
+        for z in 0..self.height {
+            for x in 0..self.width {
+                let mut sum = 0.0;
+                let mut count = 0.0;
+
+                for nz in z.saturating_sub(1)..(z + 2) {
+                    for nx in x.saturating_sub(1)..(x + 2) {
+                        if nx < self.width && nz < self.height {
+                            sum += self.values[nz * self.width + nx];
+                            count += 1.0;
+                        }
+                    }
+                }
+
+                result.push(sum / count);
+            }
+        }
+
+        self.values = result;
+    }
+
+    /// Returns the value at a world position, bilinearly interpolated between the centers of the
+    /// closest cells. Positions outside the map are clamped to the border.
+    pub fn sample(&self, position: Vector3<f32>) -> f32 {
+        if self.width == 0 || self.height == 0 {
+            return 0.0;
+        }
+
+        let fx = clamp((position.x - self.origin.x) / self.cell_size - 0.5,
+                       0.0,
+                       (self.width - 1) as f32);
+        let fz = clamp((position.z - self.origin.z) / self.cell_size - 0.5,
+                       0.0,
+                       (self.height - 1) as f32);
+
+        let x0 = fx.floor() as usize;
+        let z0 = fz.floor() as usize;
+        let x1 = cmp::min(x0 + 1, self.width - 1);
+        let z1 = cmp::min(z0 + 1, self.height - 1);
+        let tx = fx - x0 as f32;
+        let tz = fz - z0 as f32;
+
+        let top = self.get(x0, z0) * (1.0 - tx) + self.get(x1, z0) * tx;
+        let bottom = self.get(x0, z1) * (1.0 - tx) + self.get(x1, z1) * tx;
+        top * (1.0 - tz) + bottom * tz
+    }
+
+    /// Sets every cell to zero.
+    pub fn clear(&mut self) {
+        for value in &mut self.values {
+            *value = 0.0;
+        }
+    }
+}
+
+fn clamp(value: f32, min: f32, max: f32) -> f32 {
+    value.max(min).min(max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::InfluenceMap;
+    use luck_math::Vector3;
+
+    #[test]
+    fn cells() {
+        let map = InfluenceMap::new(Vector3::new(-10.0, 0.0, -10.0), 2.0, 10, 5);
+
+        assert_eq!(map.cell_at(Vector3::new(-10.0, 5.0, -10.0)), Some((0, 0)));
+        assert_eq!(map.cell_at(Vector3::new(-7.5, 0.0, -0.5)), Some((1, 4)));
+        assert_eq!(map.cell_at(Vector3::new(-10.5, 0.0, 0.0)), None);
+        assert_eq!(map.cell_at(Vector3::new(0.0, 0.0, 0.0)), None);
+        assert_eq!(map.cell_center(1, 4), Vector3::new(-7.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn deposit_decay_blur() {
+        let mut map = InfluenceMap::new(Vector3::new(0.0, 0.0, 0.0), 1.0, 5, 5);
+
+        map.deposit(Vector3::new(2.5, 0.0, 2.5), 4.0, 0.0);
+        assert_eq!(map.get(2, 2), 4.0);
+        assert_eq!(map.get(1, 2), 0.0);
+
+        map.decay(0.5);
+        assert_eq!(map.get(2, 2), 2.0);
+
+        map.blur();
+        assert_eq!(map.get(2, 2), 2.0 / 9.0);
+        assert_eq!(map.get(0, 0), 0.0);
+        assert_eq!(map.get(1, 1), 2.0 / 9.0);
+
+        map.clear();
+        map.deposit(Vector3::new(2.5, 0.0, 2.5), 1.0, 2.0);
+        assert_eq!(map.get(2, 2), 1.0);
+        assert_eq!(map.get(3, 2), 0.5);
+        assert_eq!(map.get(4, 2), 0.0);
+    }
+
+    #[test]
+    fn sample() {
+        let mut map = InfluenceMap::new(Vector3::new(0.0, 0.0, 0.0), 1.0, 2, 1);
+        map.set(0, 0, 0.0);
+        map.set(1, 0, 1.0);
+
+        assert_eq!(map.sample(Vector3::new(0.5, 0.0, 0.5)), 0.0);
+        assert_eq!(map.sample(Vector3::new(1.0, 0.0, 0.5)), 0.5);
+        assert_eq!(map.sample(Vector3::new(1.5, 0.0, 0.5)), 1.0);
+        assert_eq!(map.sample(Vector3::new(10.0, 0.0, 0.5)), 1.0);
+    }
+}
@@ -1,3 +1,33 @@
+#![warn(missing_docs)]
+#![warn(unused)]
+
+//! Engine features built on top of `luck_ecs` and `luck_math`:
+//!
+//! * `InfluenceMap`, a grid of values over the world for AI heuristics.
+//! * `compression`, varint, zigzag and delta encoding helpers to shrink serialized data.
+//! * `LifetimeComponent` and `LifetimeSystem`, to destroy entities once their time is over.
+//! * `Name` and `AssetPath`, interned strings that compare and hash as integers.
+//! * `FrameArena` and `ScratchVec`, a bump allocator for data that only lives during a frame.
+//! * `memory`, a global allocator that tracks the memory of each subsystem. Only available with
+//!   the `memory_tracking` feature.
+
+#[macro_use]
+extern crate luck_ecs;
+extern crate luck_math;
+
+pub mod influence_map;
+pub mod compression;
+pub mod lifetime;
+pub mod name;
+pub mod arena;
+#[cfg(feature = "memory_tracking")]
+pub mod memory;
+
+pub use influence_map::InfluenceMap;
+pub use lifetime::{LifetimeComponent, LifetimeSystem};
+pub use name::{AssetPath, Name};
+pub use arena::{FrameArena, ScratchVec};
+
 #[cfg(test)]
 mod test {
     #[test]
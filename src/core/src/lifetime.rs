@@ -0,0 +1,143 @@
+//! A module for entities that should only exist for a limited time, like projectiles and
+//! temporary effects. Add a `LifetimeComponent` to an entity and the `LifetimeSystem` destroys it
+//! once the time is over.
+
+use luck_ecs::{Entity, Signature, System, World};
+use std::any::TypeId;
+use std::ops::FnMut;
+use std::time::Instant;
+use std;
+
+/// The remaining time of an entity, in seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LifetimeComponent(pub f32);
+
+/// The system that counts down every `LifetimeComponent` and destroys the entities whose time is
+/// over.
+pub struct LifetimeSystem {
+    entities: Vec<Entity>,
+    time_step: Option<f32>,
+    last_update: Option<Instant>,
+}
+
+impl_signature!(LifetimeSystem, (LifetimeComponent));
+
+impl LifetimeSystem {
+    /// Constructs a `LifetimeSystem` that uses the real time elapsed between calls to
+    /// `World::process`.
+    pub fn new() -> Self {
+        LifetimeSystem {
+            entities: Vec::new(),
+            time_step: None,
+            last_update: None,
+        }
+    }
+
+    /// Constructs a `LifetimeSystem` that considers every call to `World::process` to take
+    /// exactly `seconds`. Useful for fixed time step simulations and tests.
+    pub fn with_time_step(seconds: f32) -> Self {
+        LifetimeSystem {
+            entities: Vec::new(),
+            time_step: Some(seconds),
+            last_update: None,
+        }
+    }
+
+    // Returns how many seconds passed since the last update.
+    fn advance(&mut self, now: Instant) -> f32 {
+        let last_update = self.last_update;
+        self.last_update = Some(now);
+
+        match (self.time_step, last_update) {
+            (Some(time_step), _) => time_step,
+            (None, Some(last_update)) => {
+                let elapsed = now.duration_since(last_update);
+                elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000.0
+            }
+            (None, None) => 0.0,
+        }
+    }
+}
+
+impl Default for LifetimeSystem {
+    fn default() -> Self {
+        LifetimeSystem::new()
+    }
+}
+
+impl System for LifetimeSystem {
+    fn has_entity(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    fn on_entity_added(&mut self, entity: Entity) {
+        self.entities.push(entity);
+    }
+
+    fn on_entity_removed(&mut self, entity: Entity) {
+        self.entities.retain(|&x| x != entity);
+    }
+
+    fn process(&self, _: &World) -> Box<FnMut(&mut World) + Send + Sync> {
+        let now = Instant::now();
+
+        Box::new(move |w: &mut World| {
+            let (entities, elapsed) = {
+                let system = w.get_system_mut::<LifetimeSystem>()
+                              .expect("LifetimeSystem is processing but is not in the World");
+                let elapsed = system.advance(now);
+                (system.entities.clone(), elapsed)
+            };
+
+            for entity in entities {
+                let expired = match w.get_component_mut::<LifetimeComponent>(entity) {
+                    Some(lifetime) => {
+                        lifetime.0 -= elapsed;
+                        lifetime.0 <= 0.0
+                    }
+                    None => false,
+                };
+
+                if expired && !w.is_destroy_scheduled(entity) {
+                    w.destroy_entity(entity);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LifetimeComponent, LifetimeSystem};
+    use luck_ecs::WorldBuilder;
+
+    #[test]
+    fn expiration() {
+        let mut w = WorldBuilder::new()
+                        .with_system(LifetimeSystem::with_time_step(0.5))
+                        .build();
+
+        let e1 = w.create_entity();
+        w.add_component(e1, LifetimeComponent(1.0));
+        w.apply(e1);
+
+        let e2 = w.create_entity();
+        w.add_component(e2, LifetimeComponent(0.25));
+        w.apply(e2);
+
+        let e3 = w.create_entity();
+        w.add_component(e3, LifetimeComponent(0.25));
+        w.apply(e3);
+        // Entities already scheduled by someone else are not destroyed twice.
+        w.destroy_entity(e3);
+
+        w.process();
+        assert!(w.is_valid(e1));
+        assert_eq!(*w.get_component::<LifetimeComponent>(e1).unwrap(), LifetimeComponent(0.5));
+        assert!(!w.is_valid(e2));
+        assert!(!w.is_valid(e3));
+
+        w.process();
+        assert!(!w.is_valid(e1));
+    }
+}
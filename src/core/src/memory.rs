@@ -0,0 +1,246 @@
+//! A module for the `TrackingAllocator`, a global allocator that counts the memory allocated by
+//! each subsystem of the engine. Only available with the `memory_tracking` feature. Allocations
+//! are attributed to the `MemoryTag` that is active in the allocating thread (see `with_tag`) and
+//! freeing them subtracts from the same tag, no matter which thread or tag frees them.
+
+use luck_ecs::World;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::cmp::max;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The subsystems allocations can be attributed to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemoryTag {
+    /// Allocations made outside of any call to `with_tag`.
+    Untagged = 0,
+    /// The entities, components and systems of a `World`.
+    Ecs = 1,
+    /// Loaded resources, like meshes and textures.
+    Resources = 2,
+    /// Render data, like draw lists and buffers.
+    Render = 3,
+}
+
+const TAG_COUNT: usize = 4;
+
+impl MemoryTag {
+    /// Returns every tag.
+    pub fn all() -> [MemoryTag; TAG_COUNT] {
+        [MemoryTag::Untagged, MemoryTag::Ecs, MemoryTag::Resources, MemoryTag::Render]
+    }
+}
+
+static ALLOCATED: [AtomicUsize; TAG_COUNT] = [AtomicUsize::new(0),
+                                               AtomicUsize::new(0),
+                                               AtomicUsize::new(0),
+                                               AtomicUsize::new(0)];
+static PEAK: [AtomicUsize; TAG_COUNT] = [AtomicUsize::new(0),
+                                          AtomicUsize::new(0),
+                                          AtomicUsize::new(0),
+                                          AtomicUsize::new(0)];
+static ALLOCATIONS: [AtomicUsize; TAG_COUNT] = [AtomicUsize::new(0),
+                                                 AtomicUsize::new(0),
+                                                 AtomicUsize::new(0),
+                                                 AtomicUsize::new(0)];
+
+thread_local!(static CURRENT_TAG: Cell<usize> = const { Cell::new(0) });
+
+// The tag is read while allocating, so a thread that is being torn down counts as untagged
+// instead of panicking.
+fn current_tag() -> usize {
+    CURRENT_TAG.try_with(|tag| tag.get()).unwrap_or(0)
+}
+
+/// Runs `f` with every allocation made by the current thread attributed to `tag`. The previous
+/// tag is restored afterwards, even if `f` panics.
+pub fn with_tag<R, F: FnOnce() -> R>(tag: MemoryTag, f: F) -> R {
+    struct Restore(usize);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0;
+            let _ = CURRENT_TAG.try_with(|tag| tag.set(previous));
+        }
+    }
+
+    let _restore = Restore(current_tag());
+    let _ = CURRENT_TAG.try_with(|current| current.set(tag as usize));
+    f()
+}
+
+/// A global allocator that forwards to the system allocator and keeps, for every `MemoryTag`,
+/// how many bytes are allocated, the most bytes that were allocated at the same time and how many
+/// allocations were not freed yet. It has to be installed by the executable, otherwise every
+/// statistic stays at zero. Each allocation is a few bytes larger than requested, to remember the
+/// tag it was made with.
+/// # Example
+/// ```
+/// extern crate luck_core;
+/// extern crate luck_ecs;
+///
+/// use luck_core::memory::{with_tag, MemoryStats, MemoryTag, TrackingAllocator};
+/// use luck_ecs::WorldBuilder;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+///
+/// fn main() {
+///     let mut w = with_tag(MemoryTag::Ecs, || WorldBuilder::new().build());
+///     with_tag(MemoryTag::Ecs, || w.process());
+///
+///     MemoryStats::update_resource(&mut w);
+///     let stats = w.get_resource::<MemoryStats>().unwrap().get(MemoryTag::Ecs);
+///     assert!(stats.allocated > 0);
+///     assert!(stats.peak >= stats.allocated);
+/// }
+/// ```
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TrackingAllocator;
+
+// Every allocation is preceded by a header whose last word stores the tag. The header is at least
+// a word long and keeps the alignment requested.
+fn header_size(layout: &Layout) -> usize {
+    max(layout.align(), mem::size_of::<usize>())
+}
+
+fn layout_with_header(layout: &Layout) -> Option<Layout> {
+    layout.size()
+          .checked_add(header_size(layout))
+          .and_then(|size| {
+              Layout::from_size_align(size, max(layout.align(), mem::align_of::<usize>())).ok()
+          })
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let full_layout = match layout_with_header(&layout) {
+            Some(full_layout) => full_layout,
+            None => return ptr::null_mut(),
+        };
+
+        let base = System.alloc(full_layout);
+        if base.is_null() {
+            return base;
+        }
+
+        let tag = current_tag();
+        let allocation = base.add(header_size(&layout));
+        *(allocation.sub(mem::size_of::<usize>()) as *mut usize) = tag;
+
+        let allocated = ALLOCATED[tag].fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK[tag].fetch_max(allocated, Ordering::Relaxed);
+        ALLOCATIONS[tag].fetch_add(1, Ordering::Relaxed);
+        allocation
+    }
+
+    unsafe fn dealloc(&self, allocation: *mut u8, layout: Layout) {
+        let tag = *(allocation.sub(mem::size_of::<usize>()) as *const usize);
+        ALLOCATED[tag].fetch_sub(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS[tag].fetch_sub(1, Ordering::Relaxed);
+
+        let full_layout = layout_with_header(&layout)
+                              .expect("Freed an allocation with an invalid layout");
+        System.dealloc(allocation.sub(header_size(&layout)), full_layout);
+    }
+}
+
+/// The memory attributed to a single tag.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TagStats {
+    /// The bytes currently allocated.
+    pub allocated: usize,
+    /// The most bytes that were allocated at the same time since the program started or the last
+    /// call to `reset_peaks`.
+    pub peak: usize,
+    /// The number of allocations that were not freed yet.
+    pub allocations: usize,
+}
+
+/// A snapshot of the memory attributed to each tag by the `TrackingAllocator`. The world keeps
+/// the last snapshot as a resource, see `MemoryStats::update_resource`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    tags: [TagStats; TAG_COUNT],
+}
+
+impl MemoryStats {
+    /// Returns the statistics of every tag at this moment.
+    pub fn current() -> Self {
+        let mut stats = MemoryStats::default();
+        for (index, tag) in stats.tags.iter_mut().enumerate() {
+            tag.allocated = ALLOCATED[index].load(Ordering::Relaxed);
+            tag.peak = PEAK[index].load(Ordering::Relaxed);
+            tag.allocations = ALLOCATIONS[index].load(Ordering::Relaxed);
+        }
+        stats
+    }
+
+    /// Inserts the current statistics in the world as a `MemoryStats` resource, replacing the
+    /// previous snapshot. Call it once per frame, after `World::process`.
+    pub fn update_resource(world: &mut World) {
+        world.insert_resource(MemoryStats::current());
+    }
+
+    /// Returns the statistics of a tag.
+    pub fn get(&self, tag: MemoryTag) -> TagStats {
+        self.tags[tag as usize]
+    }
+
+    /// Returns the bytes allocated by every tag together.
+    pub fn allocated(&self) -> usize {
+        self.tags.iter().map(|tag| tag.allocated).sum()
+    }
+}
+
+/// Sets the peak of every tag to the bytes it has allocated right now. Useful to measure the high
+/// water mark of a single level or scene.
+pub fn reset_peaks() {
+    for index in 0..TAG_COUNT {
+        PEAK[index].store(ALLOCATED[index].load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{with_tag, reset_peaks, MemoryStats, MemoryTag, TrackingAllocator};
+    use luck_ecs::WorldBuilder;
+
+    #[global_allocator]
+    static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+    // The only test that allocates with the render tag, so other tests running at the same time
+    // don't change its numbers.
+    #[test]
+    fn tagged_allocations() {
+        let before = MemoryStats::current().get(MemoryTag::Render);
+
+        let buffer = with_tag(MemoryTag::Render, || {
+            let buffer = vec![7u8; 1000];
+            // Nested tags are restored when they end.
+            with_tag(MemoryTag::Resources, || {});
+            let other = vec![0u16; 12];
+            (buffer, other)
+        });
+        let during = MemoryStats::current().get(MemoryTag::Render);
+        assert_eq!(during.allocated, before.allocated + 1024);
+        assert_eq!(during.allocations, before.allocations + 2);
+        assert!(during.peak >= before.allocated + 1024);
+
+        // Memory is subtracted from the tag it was allocated with.
+        drop(buffer);
+        let after = MemoryStats::current().get(MemoryTag::Render);
+        assert_eq!(after.allocated, before.allocated);
+        assert_eq!(after.allocations, before.allocations);
+        assert_eq!(after.peak, during.peak);
+        reset_peaks();
+        let reset = MemoryStats::current().get(MemoryTag::Render);
+        assert_eq!(reset.peak, after.allocated);
+
+        let mut w = WorldBuilder::new().build();
+        MemoryStats::update_resource(&mut w);
+        assert_eq!(w.get_resource::<MemoryStats>().unwrap().get(MemoryTag::Render), reset);
+        assert!(w.get_resource::<MemoryStats>().unwrap().allocated() > 0);
+    }
+}
@@ -0,0 +1,195 @@
+//! A module for interned strings. A `Name` is a 32 bit symbol that stands for a string stored
+//! once in a global table, so names, tags and paths can be copied, compared and hashed as integers.
+//! Only the first `Name::new` of each string allocates, later ones just look the string up.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+struct Interner {
+    ids: HashMap<&'static str, u32>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&mut self, string: &str) -> u32 {
+        if let Some(&id) = self.ids.get(string) {
+            return id;
+        }
+
+        assert!(self.strings.len() < u32::MAX as usize,
+                "Interned more than u32::MAX strings");
+        // Interned strings live until the program ends, so they can be handed out as 'static.
+        let string: &'static str = Box::leak(string.to_owned().into_boxed_str());
+        let id = self.strings.len() as u32;
+        self.strings.push(string);
+        self.ids.insert(string, id);
+        id
+    }
+}
+
+static INTERNER: Mutex<Option<Interner>> = Mutex::new(None);
+
+fn with_interner<R, F: FnOnce(&mut Interner) -> R>(f: F) -> R {
+    let mut interner = INTERNER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(interner.get_or_insert_with(|| {
+        Interner {
+            ids: HashMap::new(),
+            strings: Vec::new(),
+        }
+    }))
+}
+
+/// An interned string. Two names are equal if and only if their strings are equal.
+/// # Example
+/// ```
+/// use luck_core::Name;
+///
+/// let a = Name::new("player");
+/// let b = Name::new("player");
+/// assert_eq!(a, b);
+/// assert_eq!(a.as_str(), "player");
+/// assert!(a != Name::new("enemy"));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Name(u32);
+
+impl Name {
+    /// Returns the name of a string, adding it to the global table if it's not there yet.
+    pub fn new(string: &str) -> Self {
+        Name(with_interner(|interner| interner.intern(string)))
+    }
+
+    /// Returns the string this name stands for.
+    pub fn as_str(&self) -> &'static str {
+        with_interner(|interner| interner.strings[self.0 as usize])
+    }
+
+    /// Returns the symbol of the name. Symbols are only meaningful during the current execution,
+    /// the same string may get a different symbol the next time the program runs.
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+impl<'a> From<&'a str> for Name {
+    fn from(string: &'a str) -> Self {
+        Name::new(string)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Name({:?})", self.as_str())
+    }
+}
+
+/// An interned path to an asset. Backslashes are replaced by forward slashes, so the same file
+/// written with either separator is the same `AssetPath`.
+/// # Example
+/// ```
+/// use luck_core::AssetPath;
+///
+/// let path = AssetPath::new("textures\\grass.png");
+/// assert_eq!(path, AssetPath::new("textures/grass.png"));
+/// assert_eq!(path.as_str(), "textures/grass.png");
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AssetPath(Name);
+
+impl AssetPath {
+    /// Returns the interned version of a path.
+    pub fn new(path: &str) -> Self {
+        if path.contains('\\') {
+            AssetPath(Name::new(&path.replace('\\', "/")))
+        } else {
+            AssetPath(Name::new(path))
+        }
+    }
+
+    /// Returns the normalized path.
+    pub fn as_str(&self) -> &'static str {
+        self.0.as_str()
+    }
+
+    /// Returns the name of the normalized path.
+    pub fn name(&self) -> Name {
+        self.0
+    }
+}
+
+impl<'a> From<&'a str> for AssetPath {
+    fn from(path: &'a str) -> Self {
+        AssetPath::new(path)
+    }
+}
+
+impl fmt::Display for AssetPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for AssetPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AssetPath({:?})", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AssetPath, Name};
+    use std::collections::HashMap;
+    use std::thread;
+
+    #[test]
+    fn interning() {
+        let a = Name::new("interning_a");
+        let b = Name::from("interning_b");
+        assert!(a != b);
+        assert_eq!(a, Name::new(&String::from("interning_a")));
+        assert_eq!(a.id(), Name::new("interning_a").id());
+        assert_eq!(a.as_str(), "interning_a");
+        assert_eq!(b.to_string(), "interning_b");
+        assert_eq!(format!("{:?}", a), "Name(\"interning_a\")");
+        assert_eq!(Name::new("").as_str(), "");
+
+        let mut map = HashMap::new();
+        map.insert(a, 1);
+        assert_eq!(map.get(&Name::new("interning_a")), Some(&1));
+    }
+
+    #[test]
+    fn asset_paths() {
+        let path = AssetPath::new("meshes\\level\\floor.obj");
+        assert_eq!(path, AssetPath::from("meshes/level/floor.obj"));
+        assert_eq!(path.as_str(), "meshes/level/floor.obj");
+        assert_eq!(path.name(), Name::new("meshes/level/floor.obj"));
+        assert!(path != AssetPath::new("meshes/level/wall.obj"));
+    }
+
+    #[test]
+    fn threads() {
+        let threads: Vec<_> = (0..4)
+                                  .map(|_| {
+                                      thread::spawn(|| {
+                                          (0..100)
+                                              .map(|i| Name::new(&format!("threads_{}", i)))
+                                              .collect::<Vec<_>>()
+                                      })
+                                  })
+                                  .collect();
+        let names: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+        for other in &names[1..] {
+            assert_eq!(&names[0], other);
+        }
+        assert_eq!(names[0][42].as_str(), "threads_42");
+    }
+}
@@ -0,0 +1,123 @@
+//! A module with functions that put a `World` under load. They are meant to be used by benchmarks
+//! (or long running tests) so the cost of creating entities, applying them and processing the
+//! systems can be measured the same way over time.
+//! # Example
+//! ```
+//! use luck_ecs::WorldBuilder;
+//! use luck_ecs::bench;
+//!
+//! struct Position(f32, f32, f32);
+//!
+//! let mut w = WorldBuilder::new().build_with_capacity(1000);
+//! let entities = bench::spawn_entities(&mut w, 1000, |w, e| {
+//!     w.add_component(e, Position(0.0, 0.0, 0.0));
+//! });
+//!
+//! let elapsed = bench::measure(10, || {
+//!     bench::churn_components(&mut w, &entities, |_| Position(1.0, 1.0, 1.0));
+//!     w.process();
+//! });
+//! println!("{:?} per iteration", elapsed);
+//! ```
+
+use std::any::Any;
+use std::time::{Duration, Instant};
+
+use super::{Entity, World};
+
+/// Creates `count` entities, calls `init` so components can be added to each one and applies
+/// them. Returns the created entities in creation order.
+pub fn spawn_entities<F>(world: &mut World, count: usize, mut init: F) -> Vec<Entity>
+    where F: FnMut(&mut World, Entity)
+{
+    let mut entities = Vec::with_capacity(count);
+    for _ in 0..count {
+        let entity = world.create_entity();
+        init(world, entity);
+        world.apply(entity);
+        entities.push(entity);
+    }
+    entities
+}
+
+/// Removes the component `T` from every entity and adds a new one created by `make`, applying
+/// each entity twice. This exercises component storage and the signature matching of every
+/// system.
+pub fn churn_components<T, F>(world: &mut World, entities: &[Entity], mut make: F)
+    where T: Any,
+          F: FnMut(Entity) -> T
+{
+    for entity in entities {
+        world.remove_component::<T>(*entity);
+        world.apply(*entity);
+        world.add_component(*entity, make(*entity));
+        world.apply(*entity);
+    }
+}
+
+/// Schedules every entity to be destroyed and processes the world so they are actually removed.
+pub fn destroy_entities(world: &mut World, entities: &[Entity]) {
+    for entity in entities {
+        world.destroy_entity(*entity);
+    }
+    world.process();
+}
+
+/// Runs `f` `iterations` times and returns the average time of a single run.
+pub fn measure<F: FnMut()>(iterations: u32, mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+
+    if iterations > 0 {
+        start.elapsed() / iterations
+    } else {
+        Duration::new(0, 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{spawn_entities, churn_components, destroy_entities, measure};
+    use super::super::{Signature, Entity, System, World, WorldBuilder};
+    use std::ops::FnMut;
+    use std::any::TypeId;
+    use std;
+
+    #[derive(Debug, PartialEq)]
+    struct CounterComponent(u32);
+
+    #[derive(Default)]
+    struct CounterSystem {
+        entities: Vec<Entity>,
+    }
+    impl_system!(CounterSystem, (CounterComponent));
+
+    #[test]
+    fn stress_helpers() {
+        let mut w = WorldBuilder::new()
+                        .with_system(CounterSystem::default())
+                        .build();
+
+        let entities = spawn_entities(&mut w, 100, |w, e| {
+            w.add_component(e, CounterComponent(0));
+        });
+        assert_eq!(entities.len(), 100);
+        assert_eq!(w.get_system::<CounterSystem>().unwrap().entities.len(), 100);
+
+        let mut calls = 0;
+        measure(3, || {
+            churn_components(&mut w, &entities, |e| CounterComponent(e.id() as u32));
+            calls += 1;
+        });
+        assert_eq!(calls, 3);
+        assert_eq!(*w.get_component::<CounterComponent>(entities[10]).unwrap(),
+                   CounterComponent(10));
+        assert_eq!(w.get_system::<CounterSystem>().unwrap().entities.len(), 100);
+
+        destroy_entities(&mut w, &entities);
+        assert_eq!(w.get_system::<CounterSystem>().unwrap().entities.len(), 0);
+        assert!(!w.is_valid(entities[0]));
+    }
+}
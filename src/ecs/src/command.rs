@@ -0,0 +1,222 @@
+//! A module for the `CommandBuffer` type. Systems only have read access to the `World` during
+//! `System::process`, so structural changes (creating and destroying entities, adding and removing
+//! components) are recorded in a command buffer and executed later, once every callback ran.
+
+use std::any::Any;
+
+use super::{Entity, World};
+
+/// A list of structural changes to be executed on a `World`. Every change is applied (through
+/// `World::apply`) as soon as it is executed. Changes to entities that are no longer valid when
+/// the buffer is flushed are ignored.
+/// # Example
+/// ```
+/// use luck_ecs::WorldBuilder;
+///
+/// struct Health(u32);
+///
+/// let mut w = WorldBuilder::new().build();
+/// let e = w.create_entity();
+///
+/// // This would usually happen inside System::process, where only a &World is available.
+/// w.commands().add_component(e, Health(10));
+/// let created = w.commands().create_entity(&w, |w, e| {
+///     w.add_component(e, Health(5));
+/// });
+/// assert!(w.get_component::<Health>(e).is_none());
+/// assert!(!w.is_valid(created));
+///
+/// w.process();
+/// assert_eq!(w.get_component::<Health>(e).unwrap().0, 10);
+/// assert_eq!(w.get_component::<Health>(created).unwrap().0, 5);
+/// ```
+#[derive(Default)]
+pub struct CommandBuffer {
+    commands: Vec<Box<FnMut(&mut World) + Send + Sync>>,
+}
+
+impl CommandBuffer {
+    /// Constructs an empty `CommandBuffer`.
+    pub fn new() -> Self {
+        CommandBuffer { commands: Vec::new() }
+    }
+
+    /// Records the creation of an entity and returns it. The entity is reserved in `world` (see
+    /// `World::reserve_entity`), so it can be stored or used in other commands right away, but it
+    /// is only valid once the buffer is flushed into that same world. `init` is called with the
+    /// entity when the buffer is flushed so components can be added to it, the entity is applied
+    /// afterwards.
+    pub fn create_entity<F>(&mut self, world: &World, init: F) -> Entity
+        where F: FnMut(&mut World, Entity) + Send + Sync + 'static
+    {
+        let entity = world.reserve_entity();
+        let mut init = init;
+        self.commands.push(Box::new(move |w: &mut World| {
+            w.create_reserved_entities();
+            if w.is_valid(entity) {
+                init(w, entity);
+                w.apply(entity);
+            }
+        }));
+        entity
+    }
+
+    /// Records a component being added to an entity.
+    pub fn add_component<T: Any + Send + Sync>(&mut self, entity: Entity, component: T) {
+        let mut component = Some(component);
+        self.commands.push(Box::new(move |w: &mut World| {
+            if let Some(component) = component.take() {
+                if w.is_valid(entity) {
+                    w.add_component(entity, component);
+                    w.apply(entity);
+                }
+            }
+        }));
+    }
+
+    /// Records the component `T` being removed from an entity.
+    pub fn remove_component<T: Any>(&mut self, entity: Entity) {
+        self.commands.push(Box::new(move |w: &mut World| {
+            if w.is_valid(entity) {
+                w.remove_component::<T>(entity);
+                w.apply(entity);
+            }
+        }));
+    }
+
+    /// Records the destruction of an entity. Entities that were already scheduled to be
+    /// destroyed are ignored.
+    pub fn destroy_entity(&mut self, entity: Entity) {
+        self.commands.push(Box::new(move |w: &mut World| {
+            if w.is_valid(entity) && !w.is_destroy_scheduled(entity) {
+                w.destroy_entity(entity);
+            }
+        }));
+    }
+
+    /// Returns the number of recorded commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns true if there are no recorded commands.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Executes every recorded command in the order they were recorded and empties the buffer.
+    /// Destroyed entities are only removed at the end of the next `World::process`, like entities
+    /// destroyed through `World::destroy_entity`.
+    pub fn flush(&mut self, world: &mut World) {
+        for mut command in self.commands.drain(..) {
+            (*command)(world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CommandBuffer;
+    use super::super::{Signature, Entity, System, World, WorldBuilder};
+    use std::ops::FnMut;
+    use std::any::TypeId;
+    use std;
+
+    #[derive(Debug, PartialEq)]
+    struct SpawnerComponent(u32);
+    #[derive(Debug, PartialEq)]
+    struct SpawnedComponent;
+
+    struct SpawnerSystem {
+        entities: Vec<Entity>,
+    }
+    impl_signature!(SpawnerSystem, (SpawnerComponent));
+    impl System for SpawnerSystem {
+        fn has_entity(&self, entity: Entity) -> bool {
+            self.entities.contains(&entity)
+        }
+        fn on_entity_added(&mut self, entity: Entity) {
+            self.entities.push(entity);
+        }
+        fn on_entity_removed(&mut self, entity: Entity) {
+            self.entities.retain(|&x| x != entity);
+        }
+        fn process(&self, w: &World) -> Box<FnMut(&mut World) + Send + Sync> {
+            for entity in &self.entities {
+                let count = w.get_component::<SpawnerComponent>(*entity).unwrap().0;
+                for _ in 0..count {
+                    w.commands().create_entity(w, |w, e| {
+                        w.add_component(e, SpawnedComponent);
+                    });
+                }
+                w.commands().destroy_entity(*entity);
+            }
+            Box::new(|_| {})
+        }
+    }
+
+    #[test]
+    fn deferred_commands() {
+        let mut w = WorldBuilder::new()
+                        .with_system(SpawnerSystem { entities: Vec::new() })
+                        .build();
+
+        let e = w.create_entity();
+        w.add_component(e, SpawnerComponent(3));
+        w.apply(e);
+
+        w.process();
+        assert!(!w.is_valid(e));
+        assert_eq!(w.query::<&SpawnedComponent>().count(), 3);
+        assert!(w.commands().is_empty());
+    }
+
+    #[test]
+    fn invalid_entities_are_ignored() {
+        let mut w = WorldBuilder::new().build();
+        let e = w.create_entity();
+
+        let mut commands = CommandBuffer::new();
+        commands.add_component(e, SpawnedComponent);
+        commands.remove_component::<SpawnerComponent>(e);
+        commands.destroy_entity(e);
+        commands.destroy_entity(e);
+        assert_eq!(commands.len(), 4);
+
+        w.destroy_entity(e);
+        w.process();
+        commands.flush(&mut w);
+        assert!(commands.is_empty());
+        assert!(!w.is_valid(e));
+    }
+
+    #[test]
+    fn reserved_entities() {
+        let mut w = WorldBuilder::new().build();
+        let destroyed = w.create_entity();
+        w.destroy_entity(destroyed);
+        w.process();
+
+        let mut commands = CommandBuffer::new();
+        let e1 = commands.create_entity(&w, |w, e| {
+            w.add_component(e, SpawnerComponent(1));
+        });
+        let e2 = commands.create_entity(&w, |_, _| {});
+        commands.add_component(e2, SpawnedComponent);
+        assert!(e1 != e2);
+        assert!(!w.is_valid(e1));
+
+        // Reserved entities don't reuse ids, and entities created before the flush don't take
+        // theirs.
+        let e3 = w.create_entity();
+        assert_eq!(e3.id(), destroyed.id());
+        let e4 = w.create_entity();
+        assert!(e4 != e1 && e4 != e2);
+        assert!(w.is_valid(e1));
+
+        commands.flush(&mut w);
+        assert_eq!(w.get_component::<SpawnerComponent>(e1), Some(&SpawnerComponent(1)));
+        assert_eq!(w.get_component::<SpawnedComponent>(e2), Some(&SpawnedComponent));
+        assert_eq!(w.entities().into_iter().count(), 4);
+    }
+}
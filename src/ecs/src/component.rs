@@ -1,68 +1,84 @@
 //! A module for the `Components` type. Through a `Components` you can add and remove
 //! any type that implements `Any` and has no non-static references.
-//! Should be used through the `World` and not directly.
-extern crate anymap;
+//! Should be used through the `World` and not directly, except for iterating every component of
+//! a type (see `World::components`).
 
-use self::anymap::AnyMap;
 use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::iter::Zip;
+use std::mem;
+use std::slice;
 
-/// This type holds a `Vec<AnyMap>`. Entities are identified by their id (the 'key' of the
-/// vector) and AnyMap can hold one of each component type. An entity can only have either
-/// 0 or 1 component for a given component type. If you have entities 1 and 500 alive the
-/// vector will keep 500 `AnyMap`'s in memory. Even if you destroy every entity the memory
-/// of the components won't be freed. There's no way to "drain" the memory due to the
-/// way entity handles work.
+use mopa;
+
+/// This type holds one sparse set for each component type. Entities are identified by their id
+/// (the 'index' of the components) and an entity can only have either 0 or 1 component for a
+/// given component type. The components of a type are stored contiguously, so iterating over
+/// them (through `Components::iter` or `World::query`) doesn't touch the components of other
+/// types. Each type also keeps a vector of positions as long as the highest index that has a
+/// component of that type. Memory is not released when components are removed, use
+/// `Components::compact` to release it.
+///
+/// Every mutable access to a component (including adding it) marks it as changed. The changes are
+/// collected during a frame, each component at most once no matter how many times it is accessed,
+/// and become visible through `Components::changed` once `Components::swap_changes` is called,
+/// which the `World` does at the end of `World::process`.
+///
+/// Component types can also be double buffered: `Components::snapshot` copies every component of
+/// a type to a second buffer, which stays readable through `Components::get_previous` while the
+/// current components are modified.
 pub struct Components {
-    components: Vec<AnyMap>,
+    storages: HashMap<TypeId, Box<Storage>>,
+    previous: HashMap<TypeId, Box<Storage>>,
     signatures: Vec<Box<[TypeId]>>,
 }
 
 impl Components {
-    /// Constructs a new instance of `Components`. The internal vector is empty and will only
+    /// Constructs a new instance of `Components`. The internal vectors are empty and will only
     /// allocate when a component is added.
     #[allow(unknown_lints)]
-    #[allow(inline_always)]
+    #[allow(clippy::inline_always)]
     #[inline(always)]
     pub fn new() -> Self {
         Components {
-            components: Vec::new(),
+            storages: HashMap::new(),
+            previous: HashMap::new(),
             signatures: Vec::new(),
         }
     }
 
-    /// Constructs a new instance of `Components`. The internal vector is initialized with the
-    /// specified capacity.
+    /// Constructs a new instance of `Components`. The vector of signatures is initialized with
+    /// the specified capacity.
     #[allow(unknown_lints)]
-    #[allow(inline_always)]
+    #[allow(clippy::inline_always)]
     #[inline(always)]
     pub fn with_capacity(capacity: usize) -> Self {
         Components {
-            components: Vec::with_capacity(capacity),
-            signatures: Vec::new(),
+            storages: HashMap::new(),
+            previous: HashMap::new(),
+            signatures: Vec::with_capacity(capacity),
         }
     }
 
     /// Returns a list with every component associated with the `index`.
-    pub fn generate_signature(&mut self, index: usize) -> Box<[TypeId]> {
+    pub fn generate_signature(&self, index: usize) -> Box<[TypeId]> {
         self.signatures.get(index).cloned().unwrap_or_default()
     }
 
     /// Adds the `component` to the internal component list associated with the number
     /// `index`.
     pub fn add_component<T: Any>(&mut self, index: usize, component: T) -> &mut T {
-        while self.components.len() <= index {
-            self.components.push(AnyMap::new());
+        while self.signatures.len() <= index {
             self.signatures.push(Box::new([]));
         }
 
-        match self.components[index].insert(component) {
-            Some(_) => (),
-            None => {
-                let mut signature = Vec::new();
-                signature.extend_from_slice(&*self.signatures[index]);
-                signature.push(TypeId::of::<T>());
-                self.signatures[index] = signature.into_boxed_slice();
-            }
+        let replaced = self.storage_or_insert::<T>().insert(index, component);
+
+        if replaced.is_none() {
+            let mut signature = Vec::new();
+            signature.extend_from_slice(&self.signatures[index]);
+            signature.push(TypeId::of::<T>());
+            self.signatures[index] = signature.into_boxed_slice();
         }
 
         self.get_component_mut::<T>(index)
@@ -73,42 +89,442 @@ impl Components {
     /// component is returned. If index is out of bounds or the number is not associated with the
     /// component type, None is returned.
     pub fn get_component<T: Any>(&self, index: usize) -> Option<&T> {
-        if let Some(map) = self.components.get(index) {
-            map.get::<T>()
-        } else {
-            None
-        }
+        self.storage::<T>().and_then(|storage| storage.get(index))
     }
 
     /// If there is a component of type T associated with the number `index`, a mutable reference
-    /// to this component is returned. If index is out of bounds or the number is not associated
-    /// with the component type, None is returned.
+    /// to this component is returned and the component is marked as changed. If index is out of
+    /// bounds or the number is not associated with the component type, None is returned.
     pub fn get_component_mut<T: Any>(&mut self, index: usize) -> Option<&mut T> {
-        if let Some(map) = self.components.get_mut(index) {
-            map.get_mut::<T>()
-        } else {
-            None
+        self.storage_mut::<T>().and_then(|storage| storage.get_mut(index))
+    }
+
+    /// Adds a clone of the component `T` associated with `from` to `to`. Returns false if there
+    /// is no component of type T associated with `from`.
+    pub fn clone_component<T: Any + Clone>(&mut self, from: usize, to: usize) -> bool {
+        match self.get_component::<T>(from).cloned() {
+            Some(component) => {
+                self.add_component(to, component);
+                true
+            }
+            None => false,
         }
     }
 
     /// Removes the component `T` associated with the number `index` and returns it.
     pub fn remove_component<T: Any>(&mut self, index: usize) -> Option<T> {
-        if let Some(map) = self.components.get_mut(index) {
+        let removed = self.storage_mut::<T>().and_then(|storage| storage.remove(index));
+
+        if removed.is_some() {
             let mut signature = Vec::new();
-            signature.extend_from_slice(&*self.signatures[index]);
+            signature.extend_from_slice(&self.signatures[index]);
             signature.retain(|x| *x != TypeId::of::<T>());
             self.signatures[index] = signature.into_boxed_slice();
+        }
+        removed
+    }
+
+    /// Removes every component associated with the `index`.
+    pub fn remove_all_components(&mut self, index: usize) {
+        if let Some(signature) = self.signatures.get_mut(index) {
+            for type_id in signature.iter() {
+                if let Some(storage) = self.storages.get_mut(type_id) {
+                    storage.remove_index(index);
+                }
+            }
+            *signature = Box::new([]);
+        }
+    }
+
+    /// Returns an iterator over every component of type T and the index it is associated with.
+    /// The components are returned in storage order, which is not the index order.
+    pub fn iter<T: Any>(&self) -> ComponentIter<'_, T> {
+        ComponentIter {
+            components: self.storage::<T>()
+                            .map(|storage| storage.indices.iter().zip(storage.data.iter())),
+        }
+    }
+
+    /// Returns an iterator over mutable references to every component of type T and the index it
+    /// is associated with. The components are returned in storage order, which is not the index
+    /// order. Every component returned is marked as changed.
+    pub fn iter_mut<T: Any>(&mut self) -> ComponentIterMut<'_, T> {
+        let components = self.storage_mut::<T>().map(|storage| {
+            let SparseSet { ref indices, ref mut data, ref mut changes, .. } = *storage;
+            (indices.iter().zip(data.iter_mut()), changes)
+        });
+        ComponentIterMut { components: components }
+    }
+
+    /// Marks the component `T` associated with the `index` as changed, without accessing it.
+    pub fn mark_changed<T: Any>(&mut self, index: usize) {
+        self.storage_or_insert::<T>().changes.mark(index);
+    }
+
+    /// Returns the sorted indices of the components of type T that were changed before the last
+    /// call to `Components::swap_changes`. Components removed since then are still returned,
+    /// unless `Components::compact` dropped the storage of their type.
+    pub fn changed<T: Any>(&self) -> &[usize] {
+        match self.storage::<T>() {
+            Some(storage) => &storage.changes.changed,
+            None => &[],
+        }
+    }
+
+    /// Returns true if the component `T` associated with the `index` was changed before the last
+    /// call to `Components::swap_changes`.
+    pub fn is_changed<T: Any>(&self, index: usize) -> bool {
+        self.changed::<T>().binary_search(&index).is_ok()
+    }
+
+    /// Makes the changes made since the last call visible through `Components::changed` and starts
+    /// collecting new ones.
+    pub fn swap_changes(&mut self) {
+        for storage in self.storages.values_mut() {
+            storage.swap_changes();
+        }
+    }
+
+    /// Copies every component of type T to the previous buffer, replacing the last copy. The
+    /// memory of the previous buffer is reused between snapshots.
+    pub fn snapshot<T: Any + Clone>(&mut self) {
+        let current = self.storages
+                          .get(&TypeId::of::<T>())
+                          .and_then(|storage| storage.downcast_ref::<SparseSet<T>>());
+        let previous = self.previous
+                           .entry(TypeId::of::<T>())
+                           .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+                           .downcast_mut::<SparseSet<T>>()
+                           .expect("Storage of the wrong type. This should never happen");
+
+        match current {
+            Some(current) => previous.copy_from(current),
+            None => *previous = SparseSet::new(),
+        }
+    }
+
+    /// Returns the component of type T associated with the `index` when
+    /// `Components::snapshot::<T>` was last called, or None if it had no such component.
+    pub fn get_previous<T: Any>(&self, index: usize) -> Option<&T> {
+        self.previous_storage::<T>().and_then(|storage| storage.get(index))
+    }
+
+    /// Returns an iterator over every component of type T copied by the last call to
+    /// `Components::snapshot::<T>`, and the index it is associated with.
+    pub fn iter_previous<T: Any>(&self) -> ComponentIter<'_, T> {
+        ComponentIter {
+            components: self.previous_storage::<T>()
+                            .map(|storage| storage.indices.iter().zip(storage.data.iter())),
+        }
+    }
+
+    /// Releases the memory kept for removed components. Storages of types that have no components
+    /// are dropped (together with the changes recorded for them) and every internal vector is
+    /// shrunk to fit what is still in use. Indices are not changed.
+    pub fn compact(&mut self) {
+        let empty: Vec<TypeId> = self.storages
+                                     .iter()
+                                     .filter(|&(_, storage)| storage.is_empty())
+                                     .map(|(type_id, _)| *type_id)
+                                     .collect();
+        for type_id in empty {
+            self.storages.remove(&type_id);
+        }
+        for storage in self.storages.values_mut().chain(self.previous.values_mut()) {
+            storage.compact();
+        }
+        self.storages.shrink_to_fit();
+
+        while self.signatures.last().is_some_and(|signature| signature.is_empty()) {
+            self.signatures.pop();
+        }
+        self.signatures.shrink_to_fit();
+    }
+
+    fn storage<T: Any>(&self) -> Option<&SparseSet<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.downcast_ref::<SparseSet<T>>())
+    }
+
+    fn previous_storage<T: Any>(&self) -> Option<&SparseSet<T>> {
+        self.previous
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.downcast_ref::<SparseSet<T>>())
+    }
+
+    fn storage_mut<T: Any>(&mut self) -> Option<&mut SparseSet<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.downcast_mut::<SparseSet<T>>())
+    }
+
+    // Raw pointers to the storage of T, for read only queries.
+    pub(crate) fn storage_ptr<T: Any>(&self) -> Option<StoragePtr<T>> {
+        self.storage::<T>().map(|storage| {
+            StoragePtr {
+                positions: storage.positions.as_ptr(),
+                positions_len: storage.positions.len(),
+                indices: storage.indices.as_ptr(),
+                len: storage.indices.len(),
+                data: storage.data.as_ptr() as *mut T,
+                changes: &storage.changes as *const ChangeSet as *mut ChangeSet,
+            }
+        })
+    }
+
+    // Raw pointers to the storage of T, for queries that can mutate it.
+    pub(crate) fn storage_ptr_mut<T: Any>(&mut self) -> Option<StoragePtr<T>> {
+        self.storage_mut::<T>().map(|storage| {
+            StoragePtr {
+                positions: storage.positions.as_ptr(),
+                positions_len: storage.positions.len(),
+                indices: storage.indices.as_ptr(),
+                len: storage.indices.len(),
+                data: storage.data.as_mut_ptr(),
+                changes: &mut storage.changes,
+            }
+        })
+    }
+
+    fn storage_or_insert<T: Any>(&mut self) -> &mut SparseSet<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+            .downcast_mut::<SparseSet<T>>()
+            .expect("Storage of the wrong type. This should never happen")
+    }
+}
 
-            map.remove::<T>()
+// The operations that don't depend on the component type, so every storage can be kept in the
+// same map.
+trait Storage: mopa::Any {
+    fn remove_index(&mut self, index: usize);
+    fn is_empty(&self) -> bool;
+    fn compact(&mut self);
+    fn swap_changes(&mut self);
+}
+
+mopafy!(Storage);
+
+// The components of a single type. `data` and `indices` are packed, `positions` maps an index to
+// the position of its component in `data`. Every mutable access marks the component as changed.
+struct SparseSet<T> {
+    positions: Vec<Option<usize>>,
+    indices: Vec<usize>,
+    data: Vec<T>,
+    changes: ChangeSet,
+}
+
+impl<T> SparseSet<T> {
+    fn new() -> Self {
+        SparseSet {
+            positions: Vec::new(),
+            indices: Vec::new(),
+            data: Vec::new(),
+            changes: ChangeSet::new(),
+        }
+    }
+
+    fn position(&self, index: usize) -> Option<usize> {
+        self.positions.get(index).and_then(|p| *p)
+    }
+
+    fn insert(&mut self, index: usize, component: T) -> Option<T> {
+        if let Some(position) = self.position(index) {
+            return Some(mem::replace(&mut self.data[position], component));
+        }
+
+        if self.positions.len() <= index {
+            self.positions.resize(index + 1, None);
+        }
+        self.positions[index] = Some(self.data.len());
+        self.indices.push(index);
+        self.data.push(component);
+        None
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        self.position(index).map(|position| &self.data[position])
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        match self.position(index) {
+            Some(position) => {
+                self.changes.mark(index);
+                Some(&mut self.data[position])
+            }
+            None => None,
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> Option<T> {
+        self.position(index).map(|position| {
+            self.positions[index] = None;
+            self.indices.swap_remove(position);
+            if let Some(moved) = self.indices.get(position) {
+                self.positions[*moved] = Some(position);
+            }
+            self.data.swap_remove(position)
+        })
+    }
+}
+
+impl<T: Clone> SparseSet<T> {
+    // Copies the components of another set, reusing the memory of this one. Changes are not
+    // copied.
+    fn copy_from(&mut self, other: &SparseSet<T>) {
+        self.positions.clone_from(&other.positions);
+        self.indices.clone_from(&other.indices);
+        self.data.clone_from(&other.data);
+    }
+}
+
+impl<T: Any> Storage for SparseSet<T> {
+    fn remove_index(&mut self, index: usize) {
+        self.remove(index);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn compact(&mut self) {
+        let length = self.indices.iter().max().map_or(0, |index| index + 1);
+        self.positions.truncate(length);
+        self.positions.shrink_to_fit();
+        self.indices.shrink_to_fit();
+        self.data.shrink_to_fit();
+        self.changes.compact();
+    }
+
+    fn swap_changes(&mut self) {
+        self.changes.swap();
+    }
+}
+
+// The indices of the changed components of a single type. `changes` collects the changes of the
+// current frame and `marked` has a flag for each index, so an index is collected only once.
+// `changed` keeps the sorted changes of the previous frame.
+struct ChangeSet {
+    marked: Vec<bool>,
+    changes: Vec<usize>,
+    changed: Vec<usize>,
+}
+
+impl ChangeSet {
+    fn new() -> Self {
+        ChangeSet {
+            marked: Vec::new(),
+            changes: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+
+    fn mark(&mut self, index: usize) {
+        if self.marked.len() <= index {
+            self.marked.resize(index + 1, false);
+        }
+        if !self.marked[index] {
+            self.marked[index] = true;
+            self.changes.push(index);
+        }
+    }
+
+    fn swap(&mut self) {
+        for index in &self.changes {
+            self.marked[*index] = false;
+        }
+        mem::swap(&mut self.changes, &mut self.changed);
+        self.changes.clear();
+        self.changed.sort();
+    }
+
+    fn compact(&mut self) {
+        let length = self.changes.iter().max().map_or(0, |index| index + 1);
+        self.marked.truncate(length);
+        self.marked.shrink_to_fit();
+        self.changes.shrink_to_fit();
+        self.changed.shrink_to_fit();
+    }
+}
+
+/// Pointers to the storage of the component type T. Queries fetch components of several types
+/// for the same index through them, so fetching a component never goes through the whole
+/// `Components` while mutable references to components fetched before are alive. Only valid
+/// while no component of type T is added or removed.
+pub struct StoragePtr<T> {
+    positions: *const Option<usize>,
+    positions_len: usize,
+    indices: *const usize,
+    len: usize,
+    data: *mut T,
+    changes: *mut ChangeSet,
+}
+
+impl<T> StoragePtr<T> {
+    // The indices that have a component of type T, in storage order.
+    pub(crate) unsafe fn indices<'a>(&self) -> &'a [usize] {
+        slice::from_raw_parts(self.indices, self.len)
+    }
+
+    pub(crate) unsafe fn contains(&self, index: usize) -> bool {
+        self.position(index).is_some()
+    }
+
+    pub(crate) unsafe fn get<'a>(&self, index: usize) -> Option<&'a T> {
+        self.position(index).map(|position| &*self.data.add(position))
+    }
+
+    // Marks the component as changed, like `Components::get_component_mut`.
+    pub(crate) unsafe fn get_mut<'a>(&self, index: usize) -> Option<&'a mut T> {
+        self.position(index).map(|position| {
+            (*self.changes).mark(index);
+            &mut *self.data.add(position)
+        })
+    }
+
+    unsafe fn position(&self, index: usize) -> Option<usize> {
+        if index < self.positions_len {
+            *self.positions.add(index)
         } else {
             None
         }
     }
+}
 
-    /// Removes every component associated with the `index`.
-    pub fn remove_all_components(&mut self, index: usize) {
-        if self.components.get_mut(index).map(|map| *map = AnyMap::new()).is_some() {
-            self.signatures[index] = Box::new([]);
+/// An iterator over the components of a single type. Returned by `Components::iter`.
+pub struct ComponentIter<'a, T: 'a> {
+    components: Option<Zip<slice::Iter<'a, usize>, slice::Iter<'a, T>>>,
+}
+
+impl<'a, T: Any> Iterator for ComponentIter<'a, T> {
+    type Item = (usize, &'a T);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.components
+            .as_mut()
+            .and_then(|components| components.next())
+            .map(|(index, component)| (*index, component))
+    }
+}
+
+/// An iterator over mutable references to the components of a single type. Returned by
+/// `Components::iter_mut`.
+pub struct ComponentIterMut<'a, T: 'a> {
+    components: Option<(Zip<slice::Iter<'a, usize>, slice::IterMut<'a, T>>, &'a mut ChangeSet)>,
+}
+
+impl<'a, T: Any> Iterator for ComponentIterMut<'a, T> {
+    type Item = (usize, &'a mut T);
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.components {
+            Some((ref mut components, ref mut changes)) => {
+                components.next().map(|(index, component)| {
+                    changes.mark(*index);
+                    (*index, component)
+                })
+            }
+            None => None,
         }
     }
 }
@@ -122,7 +538,7 @@ mod test {
 
     #[test]
     fn with_reference() {
-        static INT_REF: &'static i32 = &15;
+        static INT_REF: &i32 = &15;
 
         #[derive(Debug, Eq, PartialEq)]
         struct RefHolder<'a> {
@@ -160,4 +576,122 @@ mod test {
         assert_eq!(comp_list.get_component::<FooComponent>(index).is_none(), true);
         assert_eq!(comp_list.remove_component::<FooComponent>(index).is_none(), true);
     }
+
+    #[test]
+    fn iteration() {
+        let mut comp_list = Components::new();
+        comp_list.add_component(1usize, FooComponent(1u32));
+        comp_list.add_component(2usize, 2u8);
+        comp_list.add_component(4usize, FooComponent(4u32));
+
+        let components: Vec<_> = comp_list.iter::<FooComponent>().collect();
+        assert_eq!(components, vec![(1, &FooComponent(1u32)), (4, &FooComponent(4u32))]);
+
+        for (index, component) in comp_list.iter_mut::<FooComponent>() {
+            component.0 += index as u32;
+        }
+        assert_eq!(*comp_list.get_component::<FooComponent>(4usize).unwrap(), FooComponent(8u32));
+
+        comp_list.remove_component::<FooComponent>(1usize);
+        assert_eq!(comp_list.iter::<FooComponent>().count(), 1);
+        assert_eq!(comp_list.iter::<u32>().count(), 0);
+    }
+
+    #[test]
+    fn sparse_removal() {
+        let mut comp_list = Components::new();
+        for index in 0usize..10usize {
+            comp_list.add_component(index, FooComponent(index as u32));
+        }
+
+        // Removing from the middle moves the last component to the freed position.
+        assert_eq!(comp_list.remove_component::<FooComponent>(2usize), Some(FooComponent(2u32)));
+        comp_list.remove_all_components(0usize);
+        assert_eq!(comp_list.remove_component::<FooComponent>(9usize), Some(FooComponent(9u32)));
+
+        for index in 0usize..10usize {
+            let expected = match index {
+                0 | 2 | 9 => None,
+                _ => Some(FooComponent(index as u32)),
+            };
+            assert_eq!(comp_list.get_component::<FooComponent>(index), expected.as_ref());
+        }
+
+        let mut indices: Vec<_> = comp_list.iter::<FooComponent>().map(|(i, _)| i).collect();
+        indices.sort();
+        assert_eq!(indices, vec![1, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn compaction() {
+        use super::SparseSet;
+        use std::any::TypeId;
+
+        let mut comp_list = Components::new();
+        for index in 0usize..1000usize {
+            comp_list.add_component(index, FooComponent(index as u32));
+            comp_list.add_component(index, index as u8);
+        }
+        for index in 10usize..1000usize {
+            comp_list.remove_all_components(index);
+        }
+        comp_list.remove_component::<FooComponent>(9usize);
+        for index in 0usize..10usize {
+            comp_list.remove_component::<u8>(index);
+        }
+
+        comp_list.compact();
+        assert_eq!(comp_list.signatures.len(), 9);
+        assert!(!comp_list.storages.contains_key(&TypeId::of::<u8>()));
+        {
+            let storage = comp_list.storages[&TypeId::of::<FooComponent>()]
+                              .downcast_ref::<SparseSet<FooComponent>>()
+                              .unwrap();
+            assert_eq!(storage.positions.len(), 9);
+            assert!(storage.data.capacity() < 1000);
+        }
+
+        assert_eq!(comp_list.get_component::<FooComponent>(8usize), Some(&FooComponent(8u32)));
+        assert_eq!(comp_list.get_component::<FooComponent>(500usize), None);
+        comp_list.add_component(500usize, 5u8);
+        assert_eq!(comp_list.get_component::<u8>(500usize), Some(&5u8));
+    }
+
+    #[test]
+    fn change_detection() {
+        use super::SparseSet;
+        use std::any::TypeId;
+
+        let mut comp_list = Components::new();
+        comp_list.add_component(3usize, FooComponent(3u32));
+        comp_list.add_component(1usize, FooComponent(1u32));
+        comp_list.add_component(2usize, 2u8);
+        assert!(comp_list.changed::<FooComponent>().is_empty());
+
+        comp_list.swap_changes();
+        assert_eq!(comp_list.changed::<FooComponent>(), &[1, 3]);
+        assert_eq!(comp_list.changed::<u8>(), &[2]);
+
+        comp_list.get_component::<FooComponent>(1usize);
+        for _ in 0..100 {
+            comp_list.get_component_mut::<FooComponent>(3usize);
+        }
+        comp_list.mark_changed::<u8>(2usize);
+        {
+            // Repeated accesses are only recorded once.
+            let storage = comp_list.storages[&TypeId::of::<FooComponent>()]
+                              .downcast_ref::<SparseSet<FooComponent>>()
+                              .unwrap();
+            assert_eq!(storage.changes.changes, vec![3]);
+        }
+        comp_list.swap_changes();
+        assert_eq!(comp_list.changed::<FooComponent>(), &[3]);
+        assert!(comp_list.is_changed::<u8>(2usize));
+        assert!(!comp_list.is_changed::<FooComponent>(1usize));
+
+        for _ in comp_list.iter_mut::<FooComponent>() {}
+        comp_list.swap_changes();
+        assert_eq!(comp_list.changed::<FooComponent>(), &[1, 3]);
+        assert!(comp_list.changed::<u8>().is_empty());
+    }
 }
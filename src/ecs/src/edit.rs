@@ -0,0 +1,315 @@
+//! A module for undoable edits of a `World`. Tools (gizmos, inspectors, etc.) change the world
+//! through `EditCommand`s executed by a `CommandHistory`, which can then undo and redo them.
+
+use mopa;
+use std::any::Any;
+use super::{Entity, World};
+
+/// A reversible change to a `World`. `revert` must restore the state the world had before
+/// `apply`, and `apply` must work again after a `revert`, since commands are reapplied on redo.
+pub trait EditCommand: mopa::Any + Send + Sync {
+    /// Makes the change.
+    fn apply(&mut self, world: &mut World);
+
+    /// Undoes the change.
+    fn revert(&mut self, world: &mut World);
+
+    /// Called when `next` was just applied after this command. Returning true folds `next` into
+    /// this command, so both are undone at once, and `next` is discarded. Useful for continuous
+    /// edits, like dragging an entity, that should be undone in a single step. The default
+    /// implementation never merges.
+    fn merge(&mut self, _next: &EditCommand) -> bool {
+        false
+    }
+}
+
+mopafy!(EditCommand);
+
+/// The list of executed `EditCommand`s, used to undo and redo them.
+/// # Example
+/// ```
+/// use luck_ecs::{CommandHistory, SetComponent, WorldBuilder};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Position(f32);
+///
+/// let mut w = WorldBuilder::new().build();
+/// let e = w.create_entity();
+/// let mut history = CommandHistory::new();
+///
+/// // A drag is executed as many small edits that are merged together.
+/// for x in 1..4 {
+///     history.execute(&mut w, SetComponent::new(e, Position(x as f32)));
+/// }
+/// history.seal();
+/// history.execute(&mut w, SetComponent::new(e, Position(10.0)));
+///
+/// history.undo(&mut w);
+/// assert_eq!(w.get_component::<Position>(e), Some(&Position(3.0)));
+/// history.undo(&mut w);
+/// assert_eq!(w.get_component::<Position>(e), None);
+/// history.redo(&mut w);
+/// assert_eq!(w.get_component::<Position>(e), Some(&Position(3.0)));
+/// ```
+pub struct CommandHistory {
+    undo: Vec<Box<EditCommand>>,
+    redo: Vec<Box<EditCommand>>,
+    sealed: bool,
+    limit: Option<usize>,
+}
+
+impl CommandHistory {
+    /// Returns an empty history without a limit.
+    pub fn new() -> Self {
+        CommandHistory {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            sealed: false,
+            limit: None,
+        }
+    }
+
+    /// Returns an empty history that forgets the oldest commands once it holds more than `limit`
+    /// of them.
+    pub fn with_limit(limit: usize) -> Self {
+        CommandHistory { limit: Some(limit), ..CommandHistory::new() }
+    }
+
+    /// Applies a command and records it, discarding every command that could be redone. The
+    /// command is merged with the previous one if possible, unless `seal` was called in between.
+    pub fn execute<T: EditCommand>(&mut self, world: &mut World, mut command: T) {
+        command.apply(world);
+        self.redo.clear();
+
+        let merged = !self.sealed &&
+                     self.undo.last_mut().is_some_and(|last| last.merge(&command));
+        self.sealed = false;
+        if !merged {
+            self.undo.push(Box::new(command));
+            if let Some(limit) = self.limit {
+                if self.undo.len() > limit {
+                    let excess = self.undo.len() - limit;
+                    self.undo.drain(..excess);
+                }
+            }
+        }
+    }
+
+    /// Prevents the next command from being merged with the last one. Call it when a continuous
+    /// edit ends, like when the mouse button is released.
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// Reverts the last command. Returns false if there was nothing to undo.
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        match self.undo.pop() {
+            Some(mut command) => {
+                command.revert(world);
+                self.redo.push(command);
+                self.sealed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the last undone command again. Returns false if there was nothing to redo.
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        match self.redo.pop() {
+            Some(mut command) => {
+                command.apply(world);
+                self.undo.push(command);
+                self.sealed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if there is a command to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns true if there is a command to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets every command, without reverting them.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.sealed = false;
+    }
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        CommandHistory::new()
+    }
+}
+
+/// An `EditCommand` that adds or replaces the component `T` of an entity. Reverting restores the
+/// previous component, or removes it if the entity didn't have one. Consecutive commands setting
+/// the same component of the same entity are merged. Commands targeting an entity that is no
+/// longer valid do nothing.
+pub struct SetComponent<T> {
+    entity: Entity,
+    value: T,
+    previous: Option<T>,
+}
+
+impl<T: Any + Clone + Send + Sync> SetComponent<T> {
+    /// Returns a command that sets the component of `entity` to `value`.
+    pub fn new(entity: Entity, value: T) -> Self {
+        SetComponent {
+            entity: entity,
+            value: value,
+            previous: None,
+        }
+    }
+}
+
+impl<T: Any + Clone + Send + Sync> EditCommand for SetComponent<T> {
+    fn apply(&mut self, world: &mut World) {
+        if world.is_valid(self.entity) {
+            self.previous = world.get_component::<T>(self.entity).cloned();
+            world.add_component(self.entity, self.value.clone());
+            world.apply(self.entity);
+        }
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if world.is_valid(self.entity) {
+            match self.previous {
+                Some(ref previous) => {
+                    world.add_component(self.entity, previous.clone());
+                }
+                None => {
+                    world.remove_component::<T>(self.entity);
+                }
+            }
+            world.apply(self.entity);
+        }
+    }
+
+    fn merge(&mut self, next: &EditCommand) -> bool {
+        match next.downcast_ref::<SetComponent<T>>() {
+            Some(next) if next.entity == self.entity => {
+                self.value = next.value.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CommandHistory, EditCommand, SetComponent};
+    use super::super::{Entity, World, WorldBuilder};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Position(i32);
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Name(&'static str);
+
+    struct Spawn(Option<Entity>);
+
+    impl EditCommand for Spawn {
+        fn apply(&mut self, world: &mut World) {
+            self.0 = Some(world.create_entity());
+        }
+
+        fn revert(&mut self, world: &mut World) {
+            if let Some(entity) = self.0.take() {
+                world.destroy_entity(entity);
+                world.process();
+            }
+        }
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut w = WorldBuilder::new().build();
+        let e = w.create_entity();
+        let mut history = CommandHistory::new();
+        assert!(!history.can_undo());
+        assert!(!history.undo(&mut w));
+
+        history.execute(&mut w, SetComponent::new(e, Position(1)));
+        history.execute(&mut w, SetComponent::new(e, Name("a")));
+        assert_eq!(w.get_component::<Position>(e), Some(&Position(1)));
+
+        assert!(history.undo(&mut w));
+        assert_eq!(w.get_component::<Name>(e), None);
+        assert!(history.can_redo());
+        assert!(history.redo(&mut w));
+        assert_eq!(w.get_component::<Name>(e), Some(&Name("a")));
+        assert!(!history.redo(&mut w));
+
+        // Executing a command discards the ones that could be redone.
+        history.undo(&mut w);
+        history.seal();
+        history.execute(&mut w, SetComponent::new(e, Position(2)));
+        assert!(!history.can_redo());
+
+        history.undo(&mut w);
+        assert_eq!(w.get_component::<Position>(e), Some(&Position(1)));
+        history.undo(&mut w);
+        assert_eq!(w.get_component::<Position>(e), None);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn merging() {
+        let mut w = WorldBuilder::new().build();
+        let e1 = w.create_entity();
+        let e2 = w.create_entity();
+        let mut history = CommandHistory::new();
+
+        history.execute(&mut w, SetComponent::new(e1, Position(0)));
+        history.seal();
+        for x in 1..10 {
+            history.execute(&mut w, SetComponent::new(e1, Position(x)));
+        }
+        // Different entities and different components are not merged.
+        history.execute(&mut w, SetComponent::new(e2, Position(5)));
+        history.execute(&mut w, SetComponent::new(e2, Name("b")));
+
+        history.undo(&mut w);
+        history.undo(&mut w);
+        assert_eq!(w.get_component::<Position>(e2), None);
+        assert_eq!(w.get_component::<Position>(e1), Some(&Position(9)));
+        history.undo(&mut w);
+        assert_eq!(w.get_component::<Position>(e1), Some(&Position(0)));
+
+        // Undoing seals the history, so a redo is never merged into.
+        history.redo(&mut w);
+        history.execute(&mut w, SetComponent::new(e1, Position(20)));
+        history.undo(&mut w);
+        assert_eq!(w.get_component::<Position>(e1), Some(&Position(9)));
+    }
+
+    #[test]
+    fn custom_commands_and_limit() {
+        let mut w = WorldBuilder::new().build();
+        let mut history = CommandHistory::with_limit(2);
+
+        for _ in 0..3 {
+            history.execute(&mut w, Spawn(None));
+        }
+        assert_eq!(w.entities().into_iter().count(), 3);
+
+        assert!(history.undo(&mut w));
+        assert!(history.undo(&mut w));
+        assert!(!history.undo(&mut w));
+        assert_eq!(w.entities().into_iter().count(), 1);
+
+        history.clear();
+        assert!(!history.can_redo());
+    }
+}
@@ -3,6 +3,8 @@
 //! should be used through the `World` and not directly.
 
 use std::iter;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// EntityId is a type that changes according to the pointer size of the target machines.
 /// It is supported `u64` for x64 machines and `u32` for x86 machines. Machines with
@@ -32,19 +34,31 @@ impl Entity {
 pub struct Entities {
     free_entity_ids: Vec<EntityId>,
     entities: Vec<EntityKey>,
+    // True for the ids in `free_entity_ids`, so checking if an id is in use doesn't depend on how
+    // many entities were destroyed.
+    free: Vec<bool>,
+    // True for the ids of the entities deactivated through `Entities::set_active`.
+    inactive: Vec<bool>,
+    // How many ids after the end of `entities` were handed out by `Entities::reserve_entity`.
+    reserved: AtomicUsize,
 }
 
 impl Entities {
     // Generates a new entity id and key either by reusing old ones or creating new ones.
     fn generate_entity_id(&mut self) -> (EntityId, EntityKey) {
+        // New ids are pushed after the reserved ones.
+        self.create_reserved_entities();
         let free_id = self.free_entity_ids.pop();
 
         match free_id {
             None => {
                 self.entities.push(1);
+                self.free.push(false);
+                self.inactive.push(false);
                 (self.entities.len() as EntityId - 1, 1)
             }
             Some(free_id) => {
+                self.free[free_id as usize] = false;
                 let key = unsafe { self.entities.get_unchecked(free_id as usize) };
                 (free_id, *key)
             }
@@ -59,12 +73,15 @@ impl Entities {
     /// let mut entities = Entities::new();
     /// ```
     #[allow(unknown_lints)]
-    #[allow(inline_always)]
+    #[allow(clippy::inline_always)]
     #[inline(always)]
     pub fn new() -> Self {
         Entities {
             free_entity_ids: Vec::new(),
             entities: Vec::new(),
+            free: Vec::new(),
+            inactive: Vec::new(),
+            reserved: AtomicUsize::new(0),
         }
     }
 
@@ -77,12 +94,15 @@ impl Entities {
     /// //The resulting Entities object will only allocate after the 11th entity is created.
     /// ```
     #[allow(unknown_lints)]
-    #[allow(inline_always)]
+    #[allow(clippy::inline_always)]
     #[inline(always)]
     pub fn with_capacity(capacity: usize) -> Self {
         Entities {
             free_entity_ids: Vec::with_capacity(capacity),
             entities: Vec::with_capacity(capacity),
+            free: Vec::with_capacity(capacity),
+            inactive: Vec::with_capacity(capacity),
+            reserved: AtomicUsize::new(0),
         }
     }
 
@@ -94,13 +114,44 @@ impl Entities {
     /// let entity = entities.create_entity();
     /// ```
     #[allow(unknown_lints)]
-    #[allow(inline_always)]
+    #[allow(clippy::inline_always)]
     #[inline(always)]
     pub fn create_entity(&mut self) -> Entity {
         let (id, key) = self.generate_entity_id();
         Entity { id: id, key: key }
     }
 
+    /// Returns a new entity without creating it, so it can be reserved through a shared reference
+    /// (from several threads at the same time). The entity is not valid until it is created by
+    /// `Entities::create_reserved_entities`, which also happens the next time an entity is
+    /// created. Reserved entities never reuse the ids of destroyed entities.
+    /// # Examples
+    /// ```
+    /// use luck_ecs::entity::Entities;
+    /// let mut entities: Entities = Entities::new();
+    /// let entity = entities.reserve_entity();
+    /// assert!(!entities.is_valid(entity));
+    /// entities.create_reserved_entities();
+    /// assert!(entities.is_valid(entity));
+    /// ```
+    pub fn reserve_entity(&self) -> Entity {
+        let id = self.entities.len() + self.reserved.fetch_add(1, Ordering::Relaxed);
+        Entity {
+            id: id as EntityId,
+            key: 1,
+        }
+    }
+
+    /// Creates every entity returned by `Entities::reserve_entity` since the last call.
+    pub fn create_reserved_entities(&mut self) {
+        let reserved = mem::replace(self.reserved.get_mut(), 0);
+        for _ in 0..reserved {
+            self.entities.push(1);
+            self.free.push(false);
+            self.inactive.push(false);
+        }
+    }
+
     /// Marks an entity as dead. The entity object is still in a valid state but call to
     /// `Entity::is_valid` will return false. Dead entities are ignored by the function.
     /// # Examples
@@ -114,7 +165,9 @@ impl Entities {
     pub fn destroy_entity(&mut self, entity: Entity) {
         if self.is_valid(entity) {
             self.free_entity_ids.push(entity.id);
-            self.entities[entity.id as usize] = self.entities[entity.id as usize] + 1;
+            self.free[entity.id as usize] = true;
+            self.inactive[entity.id as usize] = false;
+            self.entities[entity.id as usize] += 1;
         }
     }
 
@@ -135,6 +188,50 @@ impl Entities {
             false
         }
     }
+
+    /// Returns true if the entity is valid and was not deactivated. Inactive entities are still
+    /// valid, but the `World` removes them from its systems and its queries skip them.
+    /// # Examples
+    /// ```
+    /// use luck_ecs::entity::Entities;
+    /// let mut entities: Entities = Entities::with_capacity(1);
+    /// let entity = entities.create_entity();
+    /// assert!(entities.is_active(entity));
+    /// entities.destroy_entity(entity);
+    /// assert!(!entities.is_active(entity));
+    /// ```
+    pub fn is_active(&self, entity: Entity) -> bool {
+        self.is_valid(entity) && !self.inactive[entity.id as usize]
+    }
+
+    // Used by `World::activate` and `World::deactivate`, which also refresh the systems.
+    pub(crate) fn set_active(&mut self, entity: Entity, active: bool) {
+        assert!(self.is_valid(entity));
+        self.inactive[entity.id as usize] = !active;
+    }
+
+    /// Returns the valid entity that has the id, or None if the id is not used by any valid
+    /// entity.
+    /// # Examples
+    /// ```
+    /// use luck_ecs::entity::Entities;
+    /// let mut entities: Entities = Entities::with_capacity(1);
+    /// let entity = entities.create_entity();
+    /// assert_eq!(entities.get(entity.id()), Some(entity));
+    /// entities.destroy_entity(entity);
+    /// assert_eq!(entities.get(entity.id()), None);
+    /// ```
+    pub fn get(&self, id: EntityId) -> Option<Entity> {
+        match self.entities.get(id as usize) {
+            Some(key) if !self.free[id as usize] => {
+                Some(Entity {
+                    id: id,
+                    key: *key,
+                })
+            }
+            _ => None,
+        }
+    }
 }
 
 impl iter::IntoIterator for Entities {
@@ -172,8 +269,8 @@ impl iter::Iterator for EntitiesIntoIterator {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             if let Some(key) = self.entities.entities.get(self.index) {
-                self.index = self.index + 1;
-                if !self.entities.free_entity_ids.contains(&((self.index - 1) as EntityId)) {
+                self.index += 1;
+                if !self.entities.free[self.index - 1] {
                     return Some(Entity {
                         id: (self.index - 1) as EntityId,
                         key: *key,
@@ -199,8 +296,8 @@ impl<'a> iter::Iterator for EntitiesIterator<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             if let Some(key) = self.entities.entities.get(self.index) {
-                self.index = self.index + 1;
-                if !self.entities.free_entity_ids.contains(&((self.index - 1) as EntityId)) {
+                self.index += 1;
+                if !self.entities.free[self.index - 1] {
                     return Some(Entity {
                         id: (self.index - 1) as EntityId,
                         key: *key,
@@ -242,8 +339,8 @@ mod test {
     fn creation() {
         let mut entities: Entities = Entities::with_capacity(500_000usize);
 
-        let base = EntityId::max_value() - 500_000;
-        for i in base..EntityId::max_value() {
+        let base = EntityId::MAX - 500_000;
+        for i in base..EntityId::MAX {
             let e: Entity = entities.create_entity();
             assert_eq!(((e.id + base), e.key), (i, 1));
         }
@@ -301,12 +398,12 @@ mod test {
     #[test]
     #[allow(unknown_lints)]
     #[allow(unused_comparisons)]
-    #[allow(absurd_extreme_comparisons)]
+    #[allow(clippy::absurd_extreme_comparisons, clippy::unnecessary_cast)]
     fn type_size() {
-        let max_id = EntityId::max_value() as u64;
-        let max_usize = usize::max_value() as u64;
+        let max_id = EntityId::MAX as u64;
+        let max_usize = usize::MAX as u64;
 
-        if max_id > max_usize || EntityId::min_value() < 0 {
+        if max_id > max_usize || EntityId::MIN < 0 {
             panic!("Type must be contained by usize")
         }
     }
@@ -0,0 +1,137 @@
+//! A module for the `Events` type, the event bus of the `World`. Systems emit events with
+//! `World::emit` and read the events of the previous frame with `World::read_events`, so they can
+//! communicate without looking each other up through `World::get_system_mut`.
+extern crate anymap;
+
+use self::anymap::Map;
+use self::anymap::any::Any as MapAny;
+use std::any::Any;
+use std::mem;
+use std::sync::Mutex;
+
+type EventMap = Map<MapAny + Send + Sync>;
+
+/// Double buffered event queues, one for each event type. Events emitted during a frame are kept
+/// in a pending buffer and only become readable after `Events::swap` is called, which also drops
+/// the events of the previous frame. Since readers never see the buffer being written, events can
+/// be emitted and read at the same time during the parallel phase of `World::process`.
+/// # Example
+/// ```
+/// use luck_ecs::WorldBuilder;
+///
+/// struct Explosion(u32);
+///
+/// let mut w = WorldBuilder::new().build();
+/// w.emit(Explosion(10));
+/// assert_eq!(w.read_events::<Explosion>().len(), 0);
+///
+/// w.process();
+/// assert_eq!(w.read_events::<Explosion>()[0].0, 10);
+///
+/// w.process();
+/// assert_eq!(w.read_events::<Explosion>().len(), 0);
+/// ```
+pub struct Events {
+    pending: Mutex<EventMap>,
+    current: EventMap,
+}
+
+impl Events {
+    /// Constructs an empty `Events`.
+    pub fn new() -> Self {
+        Events {
+            pending: Mutex::new(Map::new()),
+            current: Map::new(),
+        }
+    }
+
+    /// Adds an event to the pending buffer.
+    pub fn emit<T: Any + Send + Sync>(&self, event: T) {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(events) = pending.get_mut::<Vec<T>>() {
+            events.push(event);
+        } else {
+            pending.insert(vec![event]);
+        }
+    }
+
+    /// Returns the events of type `T` emitted before the last swap.
+    pub fn read<T: Any + Send + Sync>(&self) -> &[T] {
+        match self.current.get::<Vec<T>>() {
+            Some(events) => events,
+            None => &[],
+        }
+    }
+
+    /// Makes the pending events readable and drops the events that were readable until now.
+    pub fn swap(&mut self) {
+        let pending = self.pending.get_mut().unwrap();
+        self.current = mem::replace(pending, Map::new());
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Events::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Events;
+    use super::super::{Signature, Entity, System, World, WorldBuilder};
+    use std::ops::FnMut;
+    use std::any::TypeId;
+    use std;
+
+    #[derive(Debug, PartialEq)]
+    struct PingEvent(u32);
+
+    struct PingerComponent;
+
+    struct PingSystem;
+    impl_signature!(PingSystem, (PingerComponent));
+    impl System for PingSystem {
+        fn has_entity(&self, _: Entity) -> bool {
+            false
+        }
+        fn on_entity_added(&mut self, _: Entity) {}
+        fn on_entity_removed(&mut self, _: Entity) {}
+        fn process(&self, w: &World) -> Box<FnMut(&mut World) + Send + Sync> {
+            let next = w.read_events::<PingEvent>().iter().map(|e| e.0 + 1).max().unwrap_or(0);
+            w.emit(PingEvent(next));
+            Box::new(|_| {})
+        }
+    }
+
+    #[test]
+    fn double_buffering() {
+        let mut events = Events::new();
+        events.emit(PingEvent(1));
+        events.emit(PingEvent(2));
+        events.emit(5u32);
+        assert!(events.read::<PingEvent>().is_empty());
+
+        events.swap();
+        assert_eq!(events.read::<PingEvent>(), &[PingEvent(1), PingEvent(2)]);
+        assert_eq!(events.read::<u32>(), &[5]);
+
+        events.emit(PingEvent(3));
+        assert_eq!(events.read::<PingEvent>().len(), 2);
+
+        events.swap();
+        assert_eq!(events.read::<PingEvent>(), &[PingEvent(3)]);
+        assert!(events.read::<u32>().is_empty());
+    }
+
+    #[test]
+    fn systems() {
+        let mut w = WorldBuilder::new().with_system(PingSystem).build();
+
+        w.process();
+        assert_eq!(w.read_events::<PingEvent>(), &[PingEvent(0)]);
+        w.process();
+        w.process();
+        assert_eq!(w.read_events::<PingEvent>(), &[PingEvent(2)]);
+    }
+}
@@ -0,0 +1,190 @@
+//! A module for the `Hierarchy` type. The hierarchy keeps the parent/child relations between
+//! entities so every system that needs them (transforms, UI layouts, etc.) uses the same data.
+//! The `World` owns a hierarchy and keeps it up to date when entities are destroyed.
+
+use super::Entity;
+use std::cmp::max;
+
+/// The parent/child relations between entities. Every entity has at most one parent and any
+/// number of children, kept in the order they were attached.
+/// # Example
+/// ```
+/// use luck_ecs::WorldBuilder;
+///
+/// let mut w = WorldBuilder::new().build();
+/// let parent = w.create_entity();
+/// let child = w.create_entity();
+/// let grandchild = w.create_entity();
+///
+/// w.attach(child, parent);
+/// w.attach(grandchild, child);
+/// assert_eq!(w.parent(child), Some(parent));
+/// assert_eq!(w.children(parent), &[child]);
+///
+/// w.destroy_entity_recursive(parent);
+/// w.process();
+/// assert!(!w.is_valid(grandchild));
+/// ```
+#[derive(Debug, Default)]
+pub struct Hierarchy {
+    parents: Vec<Option<Entity>>,
+    children: Vec<Vec<Entity>>,
+}
+
+impl Hierarchy {
+    /// Constructs an empty `Hierarchy`.
+    pub fn new() -> Self {
+        Hierarchy {
+            parents: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Makes `child` a child of `parent`, detaching it from its previous parent.
+    /// # Panics
+    /// Panics if `parent` is `child` or one of its descendants.
+    pub fn attach(&mut self, child: Entity, parent: Entity) {
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            assert!(entity != child, "An entity can't be attached to one of its descendants");
+            ancestor = self.parent(entity);
+        }
+
+        self.detach(child);
+
+        let (child_index, parent_index) = (child.id() as usize, parent.id() as usize);
+        self.reserve(max(child_index, parent_index) + 1);
+        self.parents[child_index] = Some(parent);
+        self.children[parent_index].push(child);
+    }
+
+    /// Removes `child` from its parent. Does nothing if the entity has no parent.
+    pub fn detach(&mut self, child: Entity) {
+        if let Some(parent) = self.parent(child) {
+            self.parents[child.id() as usize] = None;
+            self.children[parent.id() as usize].retain(|&x| x != child);
+        }
+    }
+
+    /// Returns the parent of the entity, or None if the entity is a root.
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.parents.get(entity.id() as usize).and_then(|p| *p)
+    }
+
+    /// Returns the children of the entity in the order they were attached.
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        match self.children.get(entity.id() as usize) {
+            Some(children) => children,
+            None => &[],
+        }
+    }
+
+    /// Returns the entity and every one of its descendants, parents always come before their
+    /// children.
+    pub fn descendants(&self, entity: Entity) -> Vec<Entity> {
+        let mut result = vec![entity];
+        let mut index = 0;
+        while index < result.len() {
+            let current = result[index];
+            result.extend_from_slice(self.children(current));
+            index += 1;
+        }
+        result
+    }
+
+    /// Removes the entity from the hierarchy. The entity is detached from its parent and its
+    /// children become roots.
+    pub fn remove(&mut self, entity: Entity) {
+        self.detach(entity);
+
+        if let Some(children) = self.children.get_mut(entity.id() as usize) {
+            for child in children.drain(..) {
+                self.parents[child.id() as usize] = None;
+            }
+        }
+    }
+
+    fn reserve(&mut self, size: usize) {
+        if self.parents.len() < size {
+            self.parents.resize(size, None);
+            self.children.resize(size, Vec::new());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Hierarchy;
+    use super::super::WorldBuilder;
+
+    #[test]
+    fn attach_detach() {
+        let mut w = WorldBuilder::new().build();
+        let e1 = w.create_entity();
+        let e2 = w.create_entity();
+        let e3 = w.create_entity();
+
+        let mut h = Hierarchy::new();
+        assert_eq!(h.parent(e1), None);
+        assert_eq!(h.children(e1), &[]);
+
+        h.attach(e2, e1);
+        h.attach(e3, e1);
+        assert_eq!(h.children(e1), &[e2, e3]);
+        assert_eq!(h.descendants(e1), vec![e1, e2, e3]);
+
+        h.attach(e3, e2);
+        assert_eq!(h.children(e1), &[e2]);
+        assert_eq!(h.parent(e3), Some(e2));
+        assert_eq!(h.descendants(e1), vec![e1, e2, e3]);
+
+        h.detach(e3);
+        assert_eq!(h.parent(e3), None);
+        assert_eq!(h.children(e2), &[]);
+
+        h.attach(e3, e2);
+        h.remove(e2);
+        assert_eq!(h.parent(e3), None);
+        assert_eq!(h.children(e1), &[]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn cycles() {
+        let mut w = WorldBuilder::new().build();
+        let e1 = w.create_entity();
+        let e2 = w.create_entity();
+
+        let mut h = Hierarchy::new();
+        h.attach(e2, e1);
+        h.attach(e1, e2);
+    }
+
+    #[test]
+    fn world_destruction() {
+        let mut w = WorldBuilder::new().build();
+        let parent = w.create_entity();
+        let child = w.create_entity();
+        let grandchild = w.create_entity();
+        w.attach(child, parent);
+        w.attach(grandchild, child);
+
+        w.destroy_entity(child);
+        w.process();
+        assert!(w.is_valid(grandchild));
+        assert_eq!(w.parent(grandchild), None);
+        assert_eq!(w.children(parent), &[]);
+
+        // The id of the destroyed entity is reused, it should not inherit any relation.
+        let reused = w.create_entity();
+        assert_eq!(reused.id(), child.id());
+        assert_eq!(w.children(reused), &[]);
+
+        w.attach(grandchild, parent);
+        w.destroy_entity_recursive(parent);
+        w.process();
+        assert!(!w.is_valid(parent));
+        assert!(!w.is_valid(grandchild));
+        assert!(w.is_valid(reused));
+    }
+}
@@ -0,0 +1,293 @@
+//! A module for the `JobSystem` trait. The `World` runs the parallel phase of `World::process`
+//! through a `JobSystem`, and systems can get the same `JobSystem` from the world to split their
+//! own read only work (culling, decompression, etc.) instead of creating new thread pools. Work
+//! with dependencies between its pieces is described with a `JobGraph` and run by
+//! `JobSystem::run`.
+
+use rayon;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Every graph gets a different id, so the ids of its jobs can't be used in another graph.
+static NEXT_GRAPH_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The priority of a job in a `JobGraph`. Among the jobs that are ready to run at the same time,
+/// the ones with higher priority are given the lower indices of `JobSystem::parallel_for`. Only
+/// job systems that start the indices in order, like `SerialJobSystem`, run them first.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Work the frame is waiting on, like culling.
+    High,
+    /// The default priority.
+    #[default]
+    Normal,
+    /// Work that can lag behind, like asset decompression.
+    Low,
+}
+
+/// The identifier of a job spawned in a `JobGraph`, used to declare the dependencies of other
+/// jobs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct JobId {
+    graph: usize,
+    index: usize,
+}
+
+struct Job<'a> {
+    priority: Priority,
+    dependencies: Vec<usize>,
+    job: Mutex<Option<Box<FnMut() + Send + 'a>>>,
+}
+
+/// A set of jobs and the dependencies between them. Nothing runs until the graph is given to
+/// `JobSystem::run`. Jobs can only depend on jobs spawned before them, so a graph never has
+/// cycles.
+/// # Example
+/// ```
+/// # use luck_ecs::{JobGraph, JobSystem, Priority, SerialJobSystem};
+/// # use std::sync::Mutex;
+/// let log = Mutex::new(Vec::new());
+///
+/// let mut graph = JobGraph::new();
+/// let load = graph.spawn(Priority::Low, || log.lock().unwrap().push("load"));
+/// graph.spawn_after(&[load], Priority::Normal, || log.lock().unwrap().push("decompress"));
+/// graph.spawn(Priority::High, || log.lock().unwrap().push("cull"));
+///
+/// SerialJobSystem.run(graph);
+/// assert_eq!(*log.lock().unwrap(), vec!["cull", "load", "decompress"]);
+/// ```
+pub struct JobGraph<'a> {
+    id: usize,
+    jobs: Vec<Job<'a>>,
+}
+
+impl<'a> JobGraph<'a> {
+    /// Returns an empty graph.
+    pub fn new() -> Self {
+        JobGraph {
+            id: NEXT_GRAPH_ID.fetch_add(1, Ordering::Relaxed),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Adds a job without dependencies to the graph.
+    pub fn spawn<F: FnOnce() + Send + 'a>(&mut self, priority: Priority, job: F) -> JobId {
+        self.spawn_after(&[], priority, job)
+    }
+
+    /// Adds a job that only runs after every job in `dependencies` is finished.
+    /// # Panics
+    /// Panics if one of the dependencies was not spawned in this graph.
+    pub fn spawn_after<F>(&mut self, dependencies: &[JobId], priority: Priority, job: F) -> JobId
+        where F: FnOnce() + Send + 'a
+    {
+        for dependency in dependencies {
+            assert!(dependency.graph == self.id,
+                    "The job {:?} was not spawned in this graph",
+                    dependency);
+        }
+
+        let mut job = Some(job);
+        self.jobs.push(Job {
+            priority: priority,
+            dependencies: dependencies.iter().map(|dependency| dependency.index).collect(),
+            job: Mutex::new(Some(Box::new(move || {
+                if let Some(job) = job.take() {
+                    job();
+                }
+            }))),
+        });
+        JobId {
+            graph: self.id,
+            index: self.jobs.len() - 1,
+        }
+    }
+
+    /// Returns the number of jobs in the graph.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns true if the graph has no jobs.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+}
+
+impl<'a> Default for JobGraph<'a> {
+    fn default() -> Self {
+        JobGraph::new()
+    }
+}
+
+/// A trait for types that can run jobs in parallel. Jobs receive only shared references, every
+/// function blocks until all the jobs it received are finished.
+pub trait JobSystem: Send + Sync {
+    /// Calls `job` once for every index in `0..count`. The calls may happen in any order and in
+    /// any thread.
+    fn parallel_for(&self, count: usize, job: &(Fn(usize) + Sync));
+
+    /// Runs both jobs, possibly at the same time. Use this to express two pieces of work that
+    /// don't depend on each other.
+    fn join(&self, a: &(Fn() + Sync), b: &(Fn() + Sync));
+
+    /// Runs every job of the graph, blocking until all of them are finished. The graph runs in
+    /// waves: every job whose dependencies are finished is given to a single `parallel_for`,
+    /// ordered by priority and then by the order they were spawned in, and the next wave only
+    /// starts when the whole wave is finished. A job can therefore wait for unrelated jobs of the
+    /// previous wave. `RayonJobSystem` may run the jobs of a wave in any order, so priorities
+    /// have no effect with it.
+    fn run(&self, graph: JobGraph) {
+        let jobs = graph.jobs;
+        let mut finished = vec![false; jobs.len()];
+        let mut remaining = jobs.len();
+
+        while remaining > 0 {
+            let mut ready: Vec<usize> = (0..jobs.len())
+                                            .filter(|&index| {
+                                                !finished[index] &&
+                                                jobs[index]
+                                                    .dependencies
+                                                    .iter()
+                                                    .all(|&dependency| finished[dependency])
+                                            })
+                                            .collect();
+            ready.sort_by_key(|&index| jobs[index].priority);
+
+            self.parallel_for(ready.len(), &|index| {
+                let job = jobs[ready[index]].job.lock().unwrap().take();
+                if let Some(mut job) = job {
+                    job();
+                }
+            });
+
+            for &index in &ready {
+                finished[index] = true;
+            }
+            remaining -= ready.len();
+        }
+    }
+}
+
+/// The default `JobSystem`, it runs the jobs in the global rayon thread pool.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct RayonJobSystem;
+
+impl JobSystem for RayonJobSystem {
+    fn parallel_for(&self, count: usize, job: &(Fn(usize) + Sync)) {
+        use rayon::par_iter::*;
+
+        (0..count).into_par_iter().for_each(job);
+    }
+
+    fn join(&self, a: &(Fn() + Sync), b: &(Fn() + Sync)) {
+        rayon::join(a, b);
+    }
+}
+
+/// A `JobSystem` that runs every job in the calling thread, in order. Useful to debug systems or
+/// when the order of the read only phase has to be deterministic.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct SerialJobSystem;
+
+impl JobSystem for SerialJobSystem {
+    fn parallel_for(&self, count: usize, job: &(Fn(usize) + Sync)) {
+        for index in 0..count {
+            job(index);
+        }
+    }
+
+    fn join(&self, a: &(Fn() + Sync), b: &(Fn() + Sync)) {
+        a();
+        b();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JobGraph, JobSystem, Priority, RayonJobSystem, SerialJobSystem};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    fn sum_indices(jobs: &JobSystem) -> usize {
+        let sum = AtomicUsize::new(0);
+        jobs.parallel_for(1000, &|i| {
+            sum.fetch_add(i, Ordering::SeqCst);
+        });
+        sum.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn parallel_for() {
+        assert_eq!(sum_indices(&RayonJobSystem), 499_500);
+        assert_eq!(sum_indices(&SerialJobSystem), 499_500);
+    }
+
+    #[test]
+    fn join() {
+        let counter = AtomicUsize::new(0);
+        let job = || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        };
+
+        RayonJobSystem.join(&job, &job);
+        SerialJobSystem.join(&job, &job);
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+    }
+
+    fn run_graph(jobs: &JobSystem) -> Vec<&'static str> {
+        let log = Mutex::new(Vec::new());
+        let name = String::from("moved");
+
+        let mut graph = JobGraph::new();
+        let first = graph.spawn(Priority::Low, || log.lock().unwrap().push("first"));
+        let second = graph.spawn_after(&[first], Priority::High, || {
+            log.lock().unwrap().push("second")
+        });
+        {
+            // Jobs can take ownership of what they capture.
+            let log = &log;
+            graph.spawn_after(&[first, second], Priority::Normal, move || {
+                assert_eq!(name, "moved");
+                log.lock().unwrap().push("third");
+            });
+        }
+        assert_eq!(graph.len(), 3);
+
+        jobs.run(graph);
+        log.into_inner().unwrap()
+    }
+
+    #[test]
+    fn dependencies() {
+        assert_eq!(run_graph(&RayonJobSystem), vec!["first", "second", "third"]);
+        assert_eq!(run_graph(&SerialJobSystem), vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn priorities() {
+        let log = Mutex::new(Vec::new());
+
+        let mut graph = JobGraph::new();
+        graph.spawn(Priority::Low, || log.lock().unwrap().push(Priority::Low));
+        graph.spawn(Priority::Normal, || log.lock().unwrap().push(Priority::Normal));
+        graph.spawn(Priority::High, || log.lock().unwrap().push(Priority::High));
+        graph.spawn(Priority::default(), || log.lock().unwrap().push(Priority::Normal));
+
+        SerialJobSystem.run(graph);
+        assert_eq!(log.into_inner().unwrap(),
+                   vec![Priority::High, Priority::Normal, Priority::Normal, Priority::Low]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn foreign_dependency() {
+        let mut other = JobGraph::new();
+        let foreign = other.spawn(Priority::Normal, || {});
+
+        // The index of the foreign job is valid in this graph, it's still rejected.
+        let mut graph = JobGraph::new();
+        graph.spawn(Priority::Normal, || {});
+        graph.spawn_after(&[foreign], Priority::Normal, || {});
+    }
+}
@@ -1,5 +1,13 @@
 #![allow(unused_features)]
 #![warn(missing_docs)]
+// Trait objects without `dyn` and explicit field init are the style of the engine. mopafy!
+// transmutes pointers to references in the code it generates.
+#![allow(bare_trait_objects)]
+#![allow(clippy::redundant_field_names,
+         clippy::new_without_default,
+         clippy::bool_assert_comparison,
+         clippy::type_complexity,
+         clippy::transmute_ptr_to_ref)]
 
 //#![feature(test)]
 //#![feature(fnbox)]
@@ -9,14 +17,39 @@
 #[macro_use]
 extern crate mopa;
 extern crate rayon;
+#[cfg(feature = "serialization")]
+extern crate serde;
+#[cfg(feature = "serialization")]
+extern crate erased_serde;
 
 pub mod entity;
 mod component;
 #[macro_use]
 pub mod system;
 mod world;
+mod pool;
+mod job;
+mod query;
+mod command;
+mod hierarchy;
+mod event;
+mod prefab;
+mod edit;
+#[cfg(feature = "serialization")]
+mod serialization;
+pub mod bench;
 
 pub use entity::Entity;
-pub use component::Components;
+pub use component::{Components, ComponentIter, ComponentIterMut};
 pub use system::{System, Signature};
 pub use world::{World, WorldBuilder};
+pub use pool::Pool;
+pub use job::{JobGraph, JobId, JobSystem, Priority, RayonJobSystem, SerialJobSystem};
+pub use query::{Query, ReadQuery, QueryIter};
+pub use command::CommandBuffer;
+pub use hierarchy::Hierarchy;
+pub use event::Events;
+pub use prefab::Prefab;
+pub use edit::{EditCommand, CommandHistory, SetComponent};
+#[cfg(feature = "serialization")]
+pub use serialization::{ComponentRegistry, PrefabRef};
@@ -0,0 +1,233 @@
+//! A module for the `Pool` type. A `Pool` keeps a list of inactive entities created from a
+//! `Prefab`, handing them out when a new entity is needed and taking them back when the entity is
+//! not needed anymore. Useful for things that are spawned and destroyed every frame, like
+//! bullets.
+
+use super::{Entity, Prefab, World};
+
+/// A list of reusable entities. The entities are created with the components of the prefab once,
+/// when the pool is constructed, and are kept deactivated (see `World::deactivate`) while they
+/// are in the pool. Acquiring and releasing them only moves them in and out of the systems, their
+/// components stay in the world. An acquired entity keeps the values its components had when it
+/// was released, so set the ones that matter (position, velocity...) after acquiring it.
+/// # Example
+/// ```
+/// use luck_ecs::{Pool, Prefab, WorldBuilder};
+///
+/// #[derive(Clone)]
+/// struct Bullet(f32);
+///
+/// let mut w = WorldBuilder::new().build();
+/// let mut pool = Pool::new(&mut w, 10, Prefab::new().with(Bullet(100.0)));
+///
+/// let e = pool.acquire(&mut w);
+/// assert!(w.is_active(e));
+/// assert_eq!(w.get_component::<Bullet>(e).unwrap().0, 100.0);
+/// assert_eq!(pool.available(), 9);
+///
+/// pool.release(&mut w, e);
+/// assert!(!w.is_active(e));
+/// assert_eq!(pool.available(), 10);
+/// ```
+pub struct Pool {
+    prefab: Prefab,
+    free: Vec<Entity>,
+    acquired: Vec<Entity>,
+}
+
+impl Pool {
+    /// Constructs a new `Pool` and creates `size` inactive entities in the world, each one with
+    /// a clone of every component of the prefab.
+    pub fn new(world: &mut World, size: usize, prefab: Prefab) -> Self {
+        let mut free = Vec::with_capacity(size);
+        for _ in 0..size {
+            let entity = world.create_entity();
+            prefab.add_components(world, entity);
+            world.deactivate(entity);
+            free.push(entity);
+        }
+
+        Pool {
+            prefab: prefab,
+            free: free,
+            acquired: Vec::new(),
+        }
+    }
+
+    /// Activates an entity of the pool and returns it. If the pool is empty a new entity is
+    /// instantiated from the prefab, so acquiring never fails.
+    pub fn acquire(&mut self, world: &mut World) -> Entity {
+        let mut entity = None;
+        while let Some(free) = self.free.pop() {
+            // Pooled entities might have been destroyed through the World directly.
+            if world.is_valid(free) && !world.is_destroy_scheduled(free) {
+                entity = Some(free);
+                break;
+            }
+        }
+        let entity = match entity {
+            Some(entity) => {
+                world.activate(entity);
+                entity
+            }
+            None => self.prefab.instantiate(world),
+        };
+
+        self.acquired.push(entity);
+        entity
+    }
+
+    /// Returns an entity to the pool. The entity is deactivated, which removes it from every
+    /// system, but it stays alive with its components.
+    /// # Panics
+    /// Panics if the entity is invalid, was not acquired from this pool, was already released or
+    /// is scheduled to be destroyed.
+    pub fn release(&mut self, world: &mut World, entity: Entity) {
+        let position = self.acquired
+                           .iter()
+                           .position(|&acquired| acquired == entity)
+                           .expect("Released an entity that is not acquired from this pool");
+        assert!(!world.is_destroy_scheduled(entity),
+                "Released an entity that is scheduled to be destroyed");
+        self.acquired.swap_remove(position);
+
+        world.deactivate(entity);
+        self.free.push(entity);
+    }
+
+    /// Returns how many entities can be acquired before the pool has to create new ones.
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pool;
+    use super::super::{Signature, Entity, Prefab, System, World, WorldBuilder};
+    use std::ops::FnMut;
+    use std::any::TypeId;
+    use std;
+
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    struct BulletComponent(u32);
+
+    #[derive(Default)]
+    struct BulletSystem {
+        entities: Vec<Entity>,
+    }
+    impl_system!(BulletSystem, (BulletComponent));
+
+    #[test]
+    fn acquire_release() {
+        let mut w = WorldBuilder::new()
+                        .with_system(BulletSystem::default())
+                        .build();
+        let mut pool = Pool::new(&mut w, 2, Prefab::new().with(BulletComponent(5)));
+        assert_eq!(pool.available(), 2);
+        // The pooled entities already have their components, but are hidden until acquired.
+        assert_eq!(w.components().iter::<BulletComponent>().count(), 2);
+        assert_eq!(w.query::<&BulletComponent>().count(), 0);
+        assert!(w.get_system::<BulletSystem>().unwrap().entities.is_empty());
+
+        let e1 = pool.acquire(&mut w);
+        let e2 = pool.acquire(&mut w);
+        assert_eq!(pool.available(), 0);
+        assert_eq!(*w.get_component::<BulletComponent>(e1).unwrap(), BulletComponent(5));
+        assert_eq!(w.get_system::<BulletSystem>().unwrap().entities.len(), 2);
+        assert_eq!(w.query::<&BulletComponent>().count(), 2);
+
+        // An empty pool instantiates the prefab instead of failing.
+        let e3 = pool.acquire(&mut w);
+        assert!(w.is_active(e3));
+        assert_eq!(*w.get_component::<BulletComponent>(e3).unwrap(), BulletComponent(5));
+
+        w.get_component_mut::<BulletComponent>(e1).unwrap().0 = 7;
+        pool.release(&mut w, e1);
+        assert!(w.is_valid(e1));
+        assert!(!w.is_active(e1));
+        assert!(!w.get_system::<BulletSystem>().unwrap().has_entity(e1));
+        assert_eq!(w.query::<&BulletComponent>().count(), 2);
+        assert_eq!(pool.available(), 1);
+
+        // Components are not reset, the entity comes back as it was released.
+        assert_eq!(pool.acquire(&mut w), e1);
+        assert_eq!(*w.get_component::<BulletComponent>(e1).unwrap(), BulletComponent(7));
+        assert!(w.get_system::<BulletSystem>().unwrap().has_entity(e1));
+
+        pool.release(&mut w, e1);
+        pool.release(&mut w, e2);
+        pool.release(&mut w, e3);
+        assert_eq!(pool.available(), 3);
+    }
+
+    #[test]
+    fn destroyed_entities_are_skipped() {
+        let mut w = WorldBuilder::new().build();
+        let mut pool = Pool::new(&mut w, 2, Prefab::new());
+
+        let e1 = pool.acquire(&mut w);
+        let e2 = pool.acquire(&mut w);
+        pool.release(&mut w, e1);
+        pool.release(&mut w, e2);
+        w.destroy_entity(e1);
+        w.process();
+
+        // Entities scheduled to be destroyed are skipped too.
+        w.destroy_entity(e2);
+        let e3 = pool.acquire(&mut w);
+        assert!(e3 != e1 && e3 != e2);
+        assert!(w.is_active(e3));
+        assert_eq!(pool.available(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn double_release() {
+        let mut w = WorldBuilder::new().build();
+        let mut pool = Pool::new(&mut w, 1, Prefab::new());
+
+        let e = pool.acquire(&mut w);
+        pool.release(&mut w, e);
+        pool.release(&mut w, e);
+    }
+
+    #[test]
+    #[should_panic]
+    fn release_foreign_entity() {
+        let mut w = WorldBuilder::new().build();
+        let mut pool = Pool::new(&mut w, 1, Prefab::new());
+        let mut other = Pool::new(&mut w, 1, Prefab::new());
+
+        let e = other.acquire(&mut w);
+        pool.release(&mut w, e);
+    }
+
+    #[test]
+    #[should_panic]
+    fn release_scheduled_entity() {
+        let mut w = WorldBuilder::new().build();
+        let mut pool = Pool::new(&mut w, 1, Prefab::new());
+
+        let e = pool.acquire(&mut w);
+        w.destroy_entity(e);
+        pool.release(&mut w, e);
+    }
+
+    #[test]
+    fn failed_release_keeps_the_pool_intact() {
+        let mut w = WorldBuilder::new().build();
+        let mut pool = Pool::new(&mut w, 1, Prefab::new());
+
+        let e = pool.acquire(&mut w);
+        let free = w.create_entity();
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    pool.release(&mut w, free)
+                }))
+                    .is_err());
+        assert_eq!(pool.available(), 0);
+
+        pool.release(&mut w, e);
+        assert_eq!(pool.available(), 1);
+    }
+}
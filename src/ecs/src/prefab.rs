@@ -0,0 +1,107 @@
+//! A module for the `Prefab` type, a template of components used to spawn similar entities
+//! (enemies, bullets, pickups) without repeating the code that builds them.
+
+use std::any::Any;
+
+use super::{Entity, World};
+
+/// A bundle of components that can be instantiated any number of times. Every instance receives
+/// a clone of each component.
+/// # Example
+/// ```
+/// use luck_ecs::{Prefab, WorldBuilder};
+///
+/// #[derive(Clone)]
+/// struct Health(u32);
+/// #[derive(Clone)]
+/// struct Speed(f32);
+///
+/// let mut w = WorldBuilder::new().build();
+/// let enemy = Prefab::new().with(Health(100)).with(Speed(2.0));
+///
+/// let e1 = enemy.instantiate(&mut w);
+/// let e2 = enemy.instantiate(&mut w);
+/// w.get_component_mut::<Health>(e1).unwrap().0 = 50;
+/// assert_eq!(w.get_component::<Health>(e2).unwrap().0, 100);
+/// ```
+#[derive(Default)]
+pub struct Prefab {
+    components: Vec<Box<Fn(&mut World, Entity) + Send + Sync>>,
+}
+
+impl Prefab {
+    /// Constructs an empty `Prefab`.
+    pub fn new() -> Self {
+        Prefab { components: Vec::new() }
+    }
+
+    /// Adds a component to the prefab. If a component of the same type was already added, the
+    /// new one overwrites it when the prefab is instantiated.
+    pub fn with<T: Any + Clone + Send + Sync>(mut self, component: T) -> Self {
+        self.components.push(Box::new(move |w: &mut World, e: Entity| {
+            w.add_component(e, component.clone());
+        }));
+        self
+    }
+
+    /// Returns the number of components added to the prefab.
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns true if no component was added to the prefab.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Adds a clone of every component of the prefab to an existing entity. Don't forget to apply
+    /// after adding.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn add_components(&self, world: &mut World, entity: Entity) {
+        for component in &self.components {
+            component(world, entity);
+        }
+    }
+
+    /// Creates a new entity with a clone of every component of the prefab. The entity is applied
+    /// before it is returned.
+    pub fn instantiate(&self, world: &mut World) -> Entity {
+        let entity = world.create_entity();
+        self.add_components(world, entity);
+        world.apply(entity);
+        entity
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Prefab;
+    use super::super::{Pool, WorldBuilder};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct HealthComponent(u32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct NameComponent(String);
+
+    #[test]
+    fn instantiation() {
+        let mut w = WorldBuilder::new().build();
+        let prefab = Prefab::new()
+                         .with(HealthComponent(10))
+                         .with(NameComponent("enemy".to_owned()))
+                         .with(HealthComponent(20));
+        assert_eq!(prefab.len(), 3);
+
+        let e1 = prefab.instantiate(&mut w);
+        let e2 = prefab.instantiate(&mut w);
+        assert!(e1 != e2);
+        assert_eq!(w.get_component::<HealthComponent>(e1), Some(&HealthComponent(20)));
+        assert_eq!(w.get_component::<NameComponent>(e2),
+                   Some(&NameComponent("enemy".to_owned())));
+
+        let mut pool = Pool::new(&mut w, 2, prefab);
+        let e3 = pool.acquire(&mut w);
+        assert_eq!(w.get_component::<HealthComponent>(e3), Some(&HealthComponent(20)));
+    }
+}
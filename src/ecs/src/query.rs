@@ -0,0 +1,326 @@
+//! A module for the `Query` trait. Queries describe a set of components (`&T` for read only
+//! access, `&mut T` for mutable access, or a tuple of both) and are used through `World::query`
+//! and `World::query_mut` to iterate over every entity that has all of them, so systems don't need
+//! to keep their own list of entities.
+
+use std::any::{Any, TypeId};
+use std::slice;
+
+use super::{Components, Entity};
+use super::component::StoragePtr;
+use super::entity::{Entities, EntityId};
+
+/// A set of components that can be fetched for a single entity. Implemented for `&T`, `&mut T`
+/// and tuples of up to 8 queries. There should be no need to implement it for other types.
+/// # Safety
+/// `Query::types` must push the type of every component the query fetches, since that is what
+/// `World::query_mut` relies on to never return two mutable references to the same component.
+/// # Example
+/// ```
+/// use luck_ecs::WorldBuilder;
+///
+/// struct Position(f32);
+/// struct Velocity(f32);
+///
+/// let mut w = WorldBuilder::new().build();
+/// let e = w.create_entity();
+/// w.add_component(e, Position(0.0));
+/// w.add_component(e, Velocity(2.0));
+///
+/// for (_, (position, velocity)) in w.query_mut::<(&mut Position, &Velocity)>() {
+///     position.0 += velocity.0;
+/// }
+///
+/// // Read only queries only need a shared reference, so they work during `System::process`.
+/// for (entity, position) in w.query::<&Position>() {
+///     assert_eq!(entity, e);
+///     assert_eq!(position.0, 2.0);
+/// }
+/// ```
+pub unsafe trait Query<'a> {
+    /// The type returned for every entity that matches the query.
+    type Item;
+
+    /// Pointers to the storages of the components the query accesses.
+    type Storages;
+
+    /// Pushes the type of every component the query accesses.
+    fn types(types: &mut Vec<TypeId>);
+
+    /// Returns the storages of the components, or None if a component type has no storage, in
+    /// which case no index can match the query.
+    /// # Safety
+    /// `components` must be valid for 'a and no component can be added or removed during 'a.
+    /// Mutable queries require it to come from a mutable reference that is not used in any other
+    /// way during 'a.
+    unsafe fn storages(components: *mut Components) -> Option<Self::Storages>;
+
+    /// Returns the indices of the component type that has the fewest components. Only these
+    /// indices can match the query.
+    /// # Safety
+    /// `storages` must be returned by `Query::storages` and be valid for 'a.
+    unsafe fn indices(storages: &Self::Storages) -> &'a [usize];
+
+    /// Returns true if every component of the query is associated with the `index`.
+    /// # Safety
+    /// `storages` must be returned by `Query::storages` and be valid for 'a.
+    unsafe fn contains(storages: &Self::Storages, index: usize) -> bool;
+
+    /// Returns the components associated with the `index`. Mutable components are marked as
+    /// changed.
+    /// # Safety
+    /// `storages` must be returned by `Query::storages` and be valid for 'a, `Query::contains`
+    /// must return true for the `index` and the same index can't be fetched again while the
+    /// returned item is alive.
+    unsafe fn fetch(storages: &Self::Storages, index: usize) -> Self::Item;
+}
+
+/// A marker for queries that only read components. Only these queries can be used through
+/// `World::query`.
+/// # Safety
+/// Implementors can only fetch shared references.
+pub unsafe trait ReadQuery<'a>: Query<'a> {}
+
+unsafe impl<'a, T: Any> Query<'a> for &'a T {
+    type Item = &'a T;
+    type Storages = StoragePtr<T>;
+
+    fn types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    unsafe fn storages(components: *mut Components) -> Option<Self::Storages> {
+        (*components).storage_ptr::<T>()
+    }
+
+    unsafe fn indices(storages: &Self::Storages) -> &'a [usize] {
+        storages.indices()
+    }
+
+    unsafe fn contains(storages: &Self::Storages, index: usize) -> bool {
+        storages.contains(index)
+    }
+
+    unsafe fn fetch(storages: &Self::Storages, index: usize) -> Self::Item {
+        storages.get(index).expect("Fetched a missing component. This should never happen")
+    }
+}
+
+unsafe impl<'a, T: Any> ReadQuery<'a> for &'a T {}
+
+unsafe impl<'a, T: Any> Query<'a> for &'a mut T {
+    type Item = &'a mut T;
+    type Storages = StoragePtr<T>;
+
+    fn types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    unsafe fn storages(components: *mut Components) -> Option<Self::Storages> {
+        (*components).storage_ptr_mut::<T>()
+    }
+
+    unsafe fn indices(storages: &Self::Storages) -> &'a [usize] {
+        storages.indices()
+    }
+
+    unsafe fn contains(storages: &Self::Storages, index: usize) -> bool {
+        storages.contains(index)
+    }
+
+    unsafe fn fetch(storages: &Self::Storages, index: usize) -> Self::Item {
+        storages.get_mut(index).expect("Fetched a missing component. This should never happen")
+    }
+}
+
+macro_rules! impl_query {
+    ( $( $name:ident $storage:ident ),+ ) => {
+        unsafe impl<'a, $( $name: Query<'a> ),+> Query<'a> for ( $( $name, )+ ) {
+            type Item = ( $( $name::Item, )+ );
+            type Storages = ( $( $name::Storages, )+ );
+
+            fn types(types: &mut Vec<TypeId>) {
+                $( $name::types(types); )+
+            }
+
+            unsafe fn storages(components: *mut Components) -> Option<Self::Storages> {
+                Some(( $(
+                    match $name::storages(components) {
+                        Some(storage) => storage,
+                        None => return None,
+                    },
+                )+ ))
+            }
+
+            unsafe fn indices(storages: &Self::Storages) -> &'a [usize] {
+                let ( $( ref $storage, )+ ) = *storages;
+                let mut indices: Option<&'a [usize]> = None;
+                $(
+                    let other = $name::indices($storage);
+                    if indices.map_or(true, |indices| other.len() < indices.len()) {
+                        indices = Some(other);
+                    }
+                )+
+                indices.unwrap_or(&[])
+            }
+
+            unsafe fn contains(storages: &Self::Storages, index: usize) -> bool {
+                let ( $( ref $storage, )+ ) = *storages;
+                true $( && $name::contains($storage, index) )+
+            }
+
+            unsafe fn fetch(storages: &Self::Storages, index: usize) -> Self::Item {
+                let ( $( ref $storage, )+ ) = *storages;
+                ( $( $name::fetch($storage, index), )+ )
+            }
+        }
+
+        unsafe impl<'a, $( $name: ReadQuery<'a> ),+> ReadQuery<'a> for ( $( $name, )+ ) {}
+    }
+}
+
+impl_query!(A a);
+impl_query!(A a, B b);
+impl_query!(A a, B b, C c);
+impl_query!(A a, B b, C c, D d);
+impl_query!(A a, B b, C c, D d, E e);
+impl_query!(A a, B b, C c, D d, E e, F f);
+impl_query!(A a, B b, C c, D d, E e, F f, G g);
+impl_query!(A a, B b, C c, D d, E e, F f, G g, H h);
+
+/// An iterator over the entities that match a query. Returned by `World::query` and
+/// `World::query_mut`. The entities are visited in the storage order of the component type that
+/// has the fewest components, so only entities that have that component are checked.
+pub struct QueryIter<'a, Q: Query<'a>> {
+    entities: &'a Entities,
+    indices: slice::Iter<'a, usize>,
+    storages: Option<Q::Storages>,
+}
+
+impl<'a, Q: Query<'a>> QueryIter<'a, Q> {
+    /// Constructs a `QueryIter` over the active entities that have every component of the
+    /// query.
+    /// # Safety
+    /// `components` must be valid for 'a and no component can be added or removed during 'a.
+    /// Read only queries can share it with other read only queries, mutable queries require it to
+    /// come from a mutable reference that is not used in any other way during 'a, and must not
+    /// access the same component type twice (see `check_unique_types`).
+    pub(crate) unsafe fn new(entities: &'a Entities, components: *mut Components) -> Self {
+        let storages = Q::storages(components);
+        let indices: &'a [usize] = match storages {
+            Some(ref storages) => Q::indices(storages),
+            None => &[],
+        };
+
+        QueryIter {
+            entities: entities,
+            indices: indices.iter(),
+            storages: storages,
+        }
+    }
+}
+
+impl<'a, Q: Query<'a>> Iterator for QueryIter<'a, Q> {
+    type Item = (Entity, Q::Item);
+    fn next(&mut self) -> Option<Self::Item> {
+        let storages = match self.storages {
+            Some(ref storages) => storages,
+            None => return None,
+        };
+
+        for index in &mut self.indices {
+            // Each index is visited only once, so the references returned never alias.
+            unsafe {
+                if Q::contains(storages, *index) {
+                    match self.entities.get(*index as EntityId) {
+                        Some(entity) if self.entities.is_active(entity) => {
+                            return Some((entity, Q::fetch(storages, *index)));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Panics if the query accesses the same component type more than once, since that would allow
+/// two mutable references to the same component.
+pub fn check_unique_types<'a, Q: Query<'a>>() {
+    let mut types = Vec::new();
+    Q::types(&mut types);
+
+    for (index, t) in types.iter().enumerate() {
+        assert!(!types[index + 1..].contains(t),
+                "A query can't access the same component type more than once");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::WorldBuilder;
+
+    #[derive(Debug, PartialEq)]
+    struct PositionComponent(f32);
+    #[derive(Debug, PartialEq)]
+    struct VelocityComponent(f32);
+
+    #[test]
+    fn query() {
+        let mut w = WorldBuilder::new().build();
+
+        let e1 = w.create_entity();
+        w.add_component(e1, PositionComponent(0.0));
+        w.add_component(e1, VelocityComponent(1.0));
+        let e2 = w.create_entity();
+        w.add_component(e2, PositionComponent(10.0));
+        let e3 = w.create_entity();
+        w.add_component(e3, PositionComponent(20.0));
+        w.add_component(e3, VelocityComponent(2.0));
+
+        for (_, (position, velocity)) in w.query_mut::<(&mut PositionComponent,
+                                                        &VelocityComponent)>() {
+            position.0 += velocity.0;
+        }
+
+        let moved: Vec<_> = w.query::<(&PositionComponent, &VelocityComponent)>()
+                             .map(|(e, (p, _))| (e, p.0))
+                             .collect();
+        assert_eq!(moved, vec![(e1, 1.0), (e3, 22.0)]);
+
+        assert_eq!(w.query::<&PositionComponent>().count(), 3);
+        assert_eq!(w.query::<&u32>().count(), 0);
+
+        // Components associated with an index that has no valid entity are skipped.
+        w.components_mut().add_component(100, VelocityComponent(3.0));
+        assert_eq!(w.query::<&VelocityComponent>().count(), 2);
+
+        w.destroy_entity(e1);
+        w.process();
+        assert_eq!(w.query::<&VelocityComponent>().count(), 1);
+    }
+
+    #[test]
+    fn change_detection() {
+        let mut w = WorldBuilder::new().build();
+
+        let e1 = w.create_entity();
+        w.add_component(e1, PositionComponent(0.0));
+        w.add_component(e1, VelocityComponent(1.0));
+        let e2 = w.create_entity();
+        w.add_component(e2, PositionComponent(10.0));
+        w.process();
+
+        for _ in w.query_mut::<(&mut PositionComponent, &VelocityComponent)>() {}
+        w.process();
+        assert_eq!(w.changed::<PositionComponent>(), vec![e1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn repeated_mutable_types() {
+        let mut w = WorldBuilder::new().build();
+        w.query_mut::<(&mut PositionComponent, &PositionComponent)>();
+    }
+}
@@ -0,0 +1,705 @@
+//! A module for the `ComponentRegistry` type, which saves and loads the entities of a `World`
+//! through serde. Only available with the `serialization` feature.
+//!
+//! A world is written as a sequence of entities, each entity being a sequence of
+//! `(name, component)` pairs. Only components whose type was registered are written, and the
+//! names given at registration are used to find the type of each component when loading, so they
+//! should not change between versions of a game.
+//!
+//! Single entities can also be exported and imported (to copy and paste them, or to store a
+//! scene as the differences from its prefabs). An exported entity is written as a tuple with the
+//! name of its prefab, the components that differ from the prefab and the names of the prefab
+//! components the entity doesn't have.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+use erased_serde;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, SerializeTuple};
+use serde::{Deserializer, Serialize, Serializer};
+
+use super::{Components, Entity, Prefab, World, WorldBuilder};
+
+type SerializeFn = fn(&Components, usize) -> Option<&erased_serde::Serialize>;
+type DeserializeFn = fn(&mut erased_serde::Deserializer, &mut World, Entity)
+                        -> Result<(), erased_serde::Error>;
+type RemoveFn = fn(&mut World, Entity);
+type EqualsFn = fn(&Components, usize, &Components, usize) -> bool;
+
+struct Registration {
+    name: String,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+    remove: RemoveFn,
+    equals: Option<EqualsFn>,
+}
+
+fn serialize_component<T: Any + Serialize>(components: &Components,
+                                           index: usize)
+                                           -> Option<&erased_serde::Serialize> {
+    components.get_component::<T>(index).map(|component| component as &erased_serde::Serialize)
+}
+
+fn deserialize_component<T>(deserializer: &mut erased_serde::Deserializer,
+                            world: &mut World,
+                            entity: Entity)
+                            -> Result<(), erased_serde::Error>
+    where T: Any + DeserializeOwned
+{
+    let component: T = erased_serde::deserialize(deserializer)?;
+    world.add_component(entity, component);
+    Ok(())
+}
+
+fn remove_component<T: Any>(world: &mut World, entity: Entity) {
+    world.remove_component::<T>(entity);
+}
+
+fn components_equal<T: Any + PartialEq>(a: &Components,
+                                        a_index: usize,
+                                        b: &Components,
+                                        b_index: usize)
+                                        -> bool {
+    match (a.get_component::<T>(a_index), b.get_component::<T>(b_index)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// The component added to the entities created through `ComponentRegistry::instantiate`, with
+/// the name of the prefab they were created from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefabRef(String);
+
+impl PrefabRef {
+    /// Returns the name the prefab was registered with.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A list of the component types that can be saved and loaded, each one with a unique name.
+/// Prefabs can be registered too, so exported entities only store how they differ from the
+/// prefab they were instantiated from.
+/// # Example
+/// ```
+/// extern crate luck_ecs;
+/// extern crate serde_json;
+///
+/// fn main() {
+///     use luck_ecs::{ComponentRegistry, WorldBuilder};
+///
+///     let registry = ComponentRegistry::new()
+///                        .with::<u32>("health")
+///                        .with::<String>("name");
+///
+///     let mut w = WorldBuilder::new().build();
+///     let e = w.create_entity();
+///     w.add_component(e, 100u32);
+///     w.add_component(e, "player".to_owned());
+///     w.apply(e);
+///
+///     let mut data = Vec::new();
+///     registry.serialize(&w, &mut serde_json::Serializer::new(&mut data)).unwrap();
+///
+///     let mut loaded = WorldBuilder::new().build();
+///     let entities = registry.deserialize(&mut loaded,
+///                                         &mut serde_json::Deserializer::from_slice(&data))
+///                            .unwrap();
+///     assert_eq!(loaded.get_component::<u32>(entities[0]), Some(&100));
+/// }
+/// ```
+pub struct ComponentRegistry {
+    types: HashMap<TypeId, Registration>,
+    names: HashMap<String, TypeId>,
+    prefabs: HashMap<String, (Prefab, Entity)>,
+    // Holds an instance of every prefab, to compare exported entities with.
+    prefab_world: World,
+}
+
+impl ComponentRegistry {
+    /// Constructs an empty `ComponentRegistry`.
+    pub fn new() -> Self {
+        ComponentRegistry {
+            types: HashMap::new(),
+            names: HashMap::new(),
+            prefabs: HashMap::new(),
+            prefab_world: WorldBuilder::new().build(),
+        }
+    }
+
+    /// Registers the component type T with the name used to identify it in the serialized data.
+    /// Exported entities always write the components of T, even when they are equal to the ones
+    /// of their prefab, see `ComponentRegistry::with_comparable`.
+    /// # Panics
+    /// Panics if the type or the name were already registered.
+    pub fn with<T: Any + Serialize + DeserializeOwned>(self, name: &str) -> Self {
+        self.register::<T>(name, None)
+    }
+
+    /// Registers the component type T like `ComponentRegistry::with`. Exported entities skip the
+    /// components of T that are equal to the ones of their prefab.
+    /// # Panics
+    /// Panics if the type or the name were already registered.
+    pub fn with_comparable<T>(self, name: &str) -> Self
+        where T: Any + Serialize + DeserializeOwned + PartialEq
+    {
+        self.register::<T>(name, Some(components_equal::<T>))
+    }
+
+    fn register<T>(mut self, name: &str, equals: Option<EqualsFn>) -> Self
+        where T: Any + Serialize + DeserializeOwned
+    {
+        assert!(!self.types.contains_key(&TypeId::of::<T>()),
+                "Component type registered twice");
+        assert!(!self.names.contains_key(name),
+                "Component name registered twice");
+
+        self.names.insert(name.to_owned(), TypeId::of::<T>());
+        self.types.insert(TypeId::of::<T>(),
+                          Registration {
+                              name: name.to_owned(),
+                              serialize: serialize_component::<T>,
+                              deserialize: deserialize_component::<T>,
+                              remove: remove_component::<T>,
+                              equals: equals,
+                          });
+        self
+    }
+
+    /// Registers a prefab with the name used to identify it in exported entities. The prefab is
+    /// instantiated once in a world owned by the registry, and exported entities are compared
+    /// with that instance.
+    /// # Panics
+    /// Panics if the name was already registered.
+    pub fn with_prefab(mut self, name: &str, prefab: Prefab) -> Self {
+        assert!(!self.prefabs.contains_key(name), "Prefab name registered twice");
+        let instance = self.prefab_world.create_entity();
+        prefab.add_components(&mut self.prefab_world, instance);
+        self.prefabs.insert(name.to_owned(), (prefab, instance));
+        self
+    }
+
+    /// Creates a new entity from the prefab registered with `name` and adds a `PrefabRef` to it,
+    /// so exporting the entity only writes how it differs from the prefab. The entity is applied
+    /// before it is returned. Returns None if there is no such prefab.
+    pub fn instantiate(&self, world: &mut World, name: &str) -> Option<Entity> {
+        self.prefabs.get(name).map(|(prefab, _)| {
+            let entity = world.create_entity();
+            prefab.add_components(world, entity);
+            world.add_component(entity, PrefabRef(name.to_owned()));
+            world.apply(entity);
+            entity
+        })
+    }
+
+    /// Writes a single entity. If the entity has a `PrefabRef` to a registered prefab, only the
+    /// registered components that differ from the prefab and the names of the prefab components
+    /// the entity no longer has are written. Otherwise every registered component is written.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    /// # Example
+    /// ```
+    /// extern crate luck_ecs;
+    /// extern crate serde_json;
+    ///
+    /// fn main() {
+    ///     use luck_ecs::{ComponentRegistry, Prefab, WorldBuilder};
+    ///
+    ///     let registry = ComponentRegistry::new()
+    ///                        .with_comparable::<u32>("health")
+    ///                        .with_comparable::<f32>("speed")
+    ///                        .with_prefab("enemy", Prefab::new().with(100u32).with(2.0f32));
+    ///
+    ///     let mut w = WorldBuilder::new().build();
+    ///     let e = registry.instantiate(&mut w, "enemy").unwrap();
+    ///     *w.get_component_mut::<u32>(e).unwrap() = 50;
+    ///
+    ///     let mut data = Vec::new();
+    ///     registry.export_entity(&w, e, &mut serde_json::Serializer::new(&mut data)).unwrap();
+    ///     assert_eq!(String::from_utf8(data.clone()).unwrap(),
+    ///                r#"["enemy",[["health",50]],[]]"#);
+    ///
+    ///     let copy = registry.import_entity(&mut w,
+    ///                                       &mut serde_json::Deserializer::from_slice(&data))
+    ///                        .unwrap();
+    ///     assert_eq!(w.get_component::<u32>(copy), Some(&50));
+    ///     assert_eq!(w.get_component::<f32>(copy), Some(&2.0));
+    /// }
+    /// ```
+    pub fn export_entity<S: Serializer>(&self,
+                                        world: &World,
+                                        entity: Entity,
+                                        serializer: S)
+                                        -> Result<S::Ok, S::Error> {
+        assert!(world.is_valid(entity));
+
+        let prefab = world.get_component::<PrefabRef>(entity)
+                          .and_then(|prefab_ref| {
+                              self.prefabs
+                                  .get(prefab_ref.name())
+                                  .map(|&(_, instance)| (prefab_ref.name(), instance))
+                          });
+
+        let index = entity.id() as usize;
+        let base_index = prefab.map(|(_, instance)| instance.id() as usize);
+        let base_components = self.prefab_world.components();
+        let signature = world.components().generate_signature(index);
+        let base_signature = match base_index {
+            Some(base_index) => base_components.generate_signature(base_index).into_vec(),
+            None => Vec::new(),
+        };
+
+        let components: Vec<ComponentData> =
+            signature.iter()
+                     .filter_map(|type_id| self.types.get(type_id))
+                     .filter(|registration| {
+                         match (registration.equals, base_index) {
+                             (Some(equals), Some(base_index)) => {
+                                 !equals(world.components(), index, base_components, base_index)
+                             }
+                             _ => true,
+                         }
+                     })
+                     .filter_map(|registration| {
+                         (registration.serialize)(world.components(), index)
+                             .map(|component| ComponentData(&registration.name[..], component))
+                     })
+                     .collect();
+        let removed: Vec<&str> = base_signature.iter()
+                                               .filter(|type_id| !signature.contains(type_id))
+                                               .filter_map(|type_id| self.types.get(type_id))
+                                               .map(|registration| &registration.name[..])
+                                               .collect();
+
+        let mut tuple = serializer.serialize_tuple(3)?;
+        tuple.serialize_element(&prefab.map(|(name, _)| name))?;
+        tuple.serialize_element(&components)?;
+        tuple.serialize_element(&removed)?;
+        tuple.end()
+    }
+
+    /// Reads an entity written by `ComponentRegistry::export_entity` and adds it to the world.
+    /// The entity is created from its prefab, then the written components are added and the
+    /// removed ones are removed. Reading fails if the prefab or a component name was not
+    /// registered, the entity stays in the world with what was read until the error.
+    pub fn import_entity<'de, D: Deserializer<'de>>(&self,
+                                                    world: &mut World,
+                                                    deserializer: D)
+                                                    -> Result<Entity, D::Error> {
+        deserializer.deserialize_tuple(3,
+                                       ImportVisitor {
+                                           registry: self,
+                                           world: world,
+                                       })
+    }
+
+    /// Writes every valid entity of the world and its registered components. The ids of the
+    /// entities and the components of types that were not registered are not written.
+    ///
+    /// Relations between entities are not saved: the parent links of the world `Hierarchy` are
+    /// not written, and components that store an `Entity` still hold the id of the saved world,
+    /// which means nothing in the world they are loaded into.
+    pub fn serialize<S: Serializer>(&self,
+                                    world: &World,
+                                    serializer: S)
+                                    -> Result<S::Ok, S::Error> {
+        let entities: Vec<Entity> = world.entities().into_iter().collect();
+        let mut seq = serializer.serialize_seq(Some(entities.len()))?;
+        for entity in entities {
+            seq.serialize_element(&EntityData {
+                registry: self,
+                world: world,
+                entity: entity,
+            })?;
+        }
+        seq.end()
+    }
+
+    /// Reads entities written by `ComponentRegistry::serialize` and adds them to the world. The
+    /// entities are created (and applied) in the order they were written and returned in that
+    /// order. Reading fails if a component name was not registered, the entities read until the
+    /// error stay in the world. The entities get new ids and no parents, see
+    /// `ComponentRegistry::serialize`.
+    pub fn deserialize<'de, D: Deserializer<'de>>(&self,
+                                                  world: &mut World,
+                                                  deserializer: D)
+                                                  -> Result<Vec<Entity>, D::Error> {
+        deserializer.deserialize_seq(WorldVisitor {
+            registry: self,
+            world: world,
+        })
+    }
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        ComponentRegistry::new()
+    }
+}
+
+struct EntityData<'a> {
+    registry: &'a ComponentRegistry,
+    world: &'a World,
+    entity: Entity,
+}
+
+impl<'a> Serialize for EntityData<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = self.entity.id() as usize;
+        let signature = self.world.components().generate_signature(index);
+        let components: Vec<(&str, &erased_serde::Serialize)> =
+            signature.iter()
+                     .filter_map(|type_id| self.registry.types.get(type_id))
+                     .filter_map(|registration| {
+                         (registration.serialize)(self.world.components(), index)
+                             .map(|component| (&registration.name[..], component))
+                     })
+                     .collect();
+
+        let mut seq = serializer.serialize_seq(Some(components.len()))?;
+        for component in components {
+            seq.serialize_element(&ComponentData(component.0, component.1))?;
+        }
+        seq.end()
+    }
+}
+
+struct ComponentData<'a>(&'a str, &'a erased_serde::Serialize);
+
+impl<'a> Serialize for ComponentData<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(self.0)?;
+        tuple.serialize_element(self.1)?;
+        tuple.end()
+    }
+}
+
+struct WorldVisitor<'a> {
+    registry: &'a ComponentRegistry,
+    world: &'a mut World,
+}
+
+impl<'a, 'de> Visitor<'de> for WorldVisitor<'a> {
+    type Value = Vec<Entity>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of entities")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entities = Vec::new();
+        while let Some(entity) = seq.next_element_seed(EntityVisitor {
+            registry: self.registry,
+            world: &mut *self.world,
+        })? {
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+}
+
+struct EntityVisitor<'a> {
+    registry: &'a ComponentRegistry,
+    world: &'a mut World,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for EntityVisitor<'a> {
+    type Value = Entity;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Entity, D::Error> {
+        let entity = self.world.create_entity();
+        let result = ComponentsVisitor {
+                         registry: self.registry,
+                         world: &mut *self.world,
+                         entity: entity,
+                     }
+                     .deserialize(deserializer);
+
+        self.world.apply(entity);
+        result.map(|_| entity)
+    }
+}
+
+// Adds a sequence of components to an existing entity.
+struct ComponentsVisitor<'a> {
+    registry: &'a ComponentRegistry,
+    world: &'a mut World,
+    entity: Entity,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ComponentsVisitor<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ComponentsVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of components")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(()) = seq.next_element_seed(ComponentVisitor {
+            registry: self.registry,
+            world: &mut *self.world,
+            entity: self.entity,
+        })? {}
+        Ok(())
+    }
+}
+
+struct ImportVisitor<'a> {
+    registry: &'a ComponentRegistry,
+    world: &'a mut World,
+}
+
+impl<'a> ImportVisitor<'a> {
+    fn read_overrides<'de, A: SeqAccess<'de>>(&mut self,
+                                              seq: &mut A,
+                                              entity: Entity)
+                                              -> Result<(), A::Error> {
+        let components = ComponentsVisitor {
+            registry: self.registry,
+            world: &mut *self.world,
+            entity: entity,
+        };
+        if seq.next_element_seed(components)?.is_none() {
+            return Err(de::Error::invalid_length(1, &"a prefab and two sequences"));
+        }
+
+        let removed: Vec<String> = match seq.next_element()? {
+            Some(removed) => removed,
+            None => return Err(de::Error::invalid_length(2, &"a prefab and two sequences")),
+        };
+        for name in removed {
+            match self.registry
+                      .names
+                      .get(&name)
+                      .and_then(|type_id| self.registry.types.get(type_id)) {
+                Some(registration) => (registration.remove)(self.world, entity),
+                None => return Err(de::Error::custom(format!("unknown component `{}`", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ImportVisitor<'a> {
+    type Value = Entity;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a prefab name, the overridden components and the removed components")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Entity, A::Error> {
+        let prefab: Option<String> = match seq.next_element()? {
+            Some(prefab) => prefab,
+            None => return Err(de::Error::invalid_length(0, &self)),
+        };
+
+        let entity = match prefab {
+            Some(name) => {
+                match self.registry.instantiate(self.world, &name) {
+                    Some(entity) => entity,
+                    None => return Err(de::Error::custom(format!("unknown prefab `{}`", name))),
+                }
+            }
+            None => self.world.create_entity(),
+        };
+
+        let result = self.read_overrides(&mut seq, entity);
+        self.world.apply(entity);
+        result.map(|_| entity)
+    }
+}
+
+struct ComponentVisitor<'a> {
+    registry: &'a ComponentRegistry,
+    world: &'a mut World,
+    entity: Entity,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ComponentVisitor<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ComponentVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a component name followed by the component")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let name: String = match seq.next_element()? {
+            Some(name) => name,
+            None => return Err(de::Error::invalid_length(0, &self)),
+        };
+
+        let registration = match self.registry
+                                     .names
+                                     .get(&name)
+                                     .and_then(|type_id| self.registry.types.get(type_id)) {
+            Some(registration) => registration,
+            None => return Err(de::Error::custom(format!("unknown component `{}`", name))),
+        };
+
+        let seed = ValueSeed {
+            deserialize: registration.deserialize,
+            world: self.world,
+            entity: self.entity,
+        };
+        match seq.next_element_seed(seed)? {
+            Some(()) => Ok(()),
+            None => Err(de::Error::invalid_length(1, &"a component name and the component")),
+        }
+    }
+}
+
+struct ValueSeed<'a> {
+    deserialize: DeserializeFn,
+    world: &'a mut World,
+    entity: Entity,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ValueSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        let mut deserializer = erased_serde::Deserializer::erase(deserializer);
+        (self.deserialize)(&mut deserializer, self.world, self.entity).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate serde_json;
+
+    use super::{ComponentRegistry, PrefabRef};
+    use super::super::{Prefab, WorldBuilder};
+
+    #[test]
+    fn round_trip() {
+        let registry = ComponentRegistry::new().with::<u32>("health").with::<String>("name");
+
+        let mut w = WorldBuilder::new().build();
+        let e1 = w.create_entity();
+        w.add_component(e1, 10u32);
+        w.add_component(e1, "first".to_owned());
+        w.add_component(e1, 1.0f32);
+        w.apply(e1);
+        let e2 = w.create_entity();
+        w.apply(e2);
+        let e3 = w.create_entity();
+        w.add_component(e3, 30u32);
+        w.apply(e3);
+        w.destroy_entity(e2);
+        w.process();
+
+        let mut data = Vec::new();
+        registry.serialize(&w, &mut serde_json::Serializer::new(&mut data)).unwrap();
+
+        let mut loaded = WorldBuilder::new().build();
+        let entities = registry.deserialize(&mut loaded,
+                                            &mut serde_json::Deserializer::from_slice(&data))
+                               .unwrap();
+        assert_eq!(entities.len(), 2);
+        assert_eq!(loaded.get_component::<u32>(entities[0]), Some(&10));
+        assert_eq!(loaded.get_component::<String>(entities[0]),
+                   Some(&"first".to_owned()));
+        assert_eq!(loaded.get_component::<f32>(entities[0]), None);
+        assert_eq!(loaded.get_component::<u32>(entities[1]), Some(&30));
+    }
+
+    #[test]
+    fn unknown_components() {
+        let mut w = WorldBuilder::new().build();
+        let result = ComponentRegistry::new()
+                         .deserialize(&mut w,
+                                      &mut serde_json::Deserializer::from_str("[[[\"a\", 1]]]"));
+        assert!(result.is_err());
+    }
+
+    fn registry() -> ComponentRegistry {
+        ComponentRegistry::new()
+            .with_comparable::<u32>("health")
+            .with_comparable::<f32>("speed")
+            .with::<String>("name")
+            .with_prefab("enemy",
+                         Prefab::new().with(100u32).with(2.0f32).with("grunt".to_owned()))
+    }
+
+    #[test]
+    fn export_import() {
+        let registry = registry();
+
+        let mut w = WorldBuilder::new().build();
+        let e = registry.instantiate(&mut w, "enemy").unwrap();
+        assert_eq!(w.get_component::<PrefabRef>(e).unwrap().name(), "enemy");
+        *w.get_component_mut::<u32>(e).unwrap() = 50;
+        w.remove_component::<f32>(e);
+        w.add_component(e, 1u8);
+        w.apply(e);
+
+        let mut data = Vec::new();
+        registry.export_entity(&w, e, &mut serde_json::Serializer::new(&mut data)).unwrap();
+        // Components that can't be compared are always written.
+        assert_eq!(String::from_utf8(data.clone()).unwrap(),
+                   r#"["enemy",[["health",50],["name","grunt"]],["speed"]]"#);
+
+        let mut loaded = WorldBuilder::new().build();
+        let copy = registry.import_entity(&mut loaded,
+                                          &mut serde_json::Deserializer::from_slice(&data))
+                           .unwrap();
+        assert_eq!(loaded.get_component::<u32>(copy), Some(&50));
+        assert_eq!(loaded.get_component::<f32>(copy), None);
+        assert_eq!(loaded.get_component::<String>(copy), Some(&"grunt".to_owned()));
+        assert_eq!(loaded.get_component::<u8>(copy), None);
+        assert_eq!(loaded.get_component::<PrefabRef>(copy).unwrap().name(), "enemy");
+    }
+
+    #[test]
+    fn export_without_prefab() {
+        let registry = registry();
+
+        let mut w = WorldBuilder::new().build();
+        let e = w.create_entity();
+        w.add_component(e, 5u32);
+        w.apply(e);
+
+        let mut data = Vec::new();
+        registry.export_entity(&w, e, &mut serde_json::Serializer::new(&mut data)).unwrap();
+        assert_eq!(String::from_utf8(data.clone()).unwrap(), r#"[null,[["health",5]],[]]"#);
+
+        let copy = registry.import_entity(&mut w,
+                                          &mut serde_json::Deserializer::from_slice(&data))
+                           .unwrap();
+        assert!(copy != e);
+        assert_eq!(w.get_component::<u32>(copy), Some(&5));
+        assert!(w.get_component::<PrefabRef>(copy).is_none());
+    }
+
+    #[test]
+    fn import_errors() {
+        let registry = registry();
+        let mut w = WorldBuilder::new().build();
+
+        for data in &[r#"["boss",[],[]]"#, r#"[null,[["mana",1]],[]]"#, r#"[null,[],["mana"]]"#] {
+            let result = registry.import_entity(&mut w,
+                                                &mut serde_json::Deserializer::from_str(data));
+            assert!(result.is_err());
+        }
+    }
+}
@@ -111,6 +111,8 @@ macro_rules! impl_signature {
     }
 }
 
+// Only used by the tests.
+#[cfg(test)]
 macro_rules! impl_system {
     ( $name:ty , ( $( $mask:path ),+ ) , $process:block ) => {
         impl_signature!($name, ( $($mask),+ ) );
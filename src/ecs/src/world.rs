@@ -1,9 +1,21 @@
+extern crate anymap;
+
 use mopa::Any;
 
-use super::entity::Entities;
+use super::entity::{Entities, EntityId};
 use super::component::Components;
 use super::{Entity, System};
+use super::job::{JobSystem, RayonJobSystem};
+use super::query::{Query, ReadQuery, QueryIter, check_unique_types};
+use super::command::CommandBuffer;
+use super::hierarchy::Hierarchy;
+use super::event::Events;
+use self::anymap::Map;
+use self::anymap::any::Any as MapAny;
 use std::any::TypeId;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Mutex, MutexGuard};
 
 /// The World type is responsible for managing the entities, components and systems. Entities
 /// created through this type are sent to systems that accept their signature.
@@ -12,9 +24,24 @@ pub struct World {
     entities: Entities,
     components: Components,
     systems: Vec<Box<System>>,
+    // The index one past the last system of each stage.
+    stages: Vec<usize>,
     to_destroy: Vec<Entity>,
+    job_system: Box<JobSystem>,
+    commands: Mutex<CommandBuffer>,
+    hierarchy: Hierarchy,
+    events: Events,
+    resources: Map<MapAny + Send + Sync>,
+    cloners: HashMap<TypeId, Cloner>,
+    snapshots: HashMap<TypeId, Snapshot>,
 }
 
+// Clones one component type from an index to another, see `World::register_cloneable`.
+type Cloner = fn(&mut Components, usize, usize) -> bool;
+
+// Copies one component type to its previous buffer, see `World::register_double_buffered`.
+type Snapshot = fn(&mut Components);
+
 unsafe impl Send for World {}
 unsafe impl Sync for World {}
 
@@ -52,53 +79,225 @@ unsafe impl Sync for World {}
 /// }
 /// ```
 pub struct WorldBuilder {
-    systems: Vec<Box<System>>,
+    systems: Vec<SystemEntry>,
+    stages: Vec<String>,
+    job_system: Box<JobSystem>,
+    resources: Map<MapAny + Send + Sync>,
+    cloners: HashMap<TypeId, Cloner>,
+    snapshots: HashMap<TypeId, Snapshot>,
 }
 
 impl WorldBuilder {
     /// Constructs a new WorldBuilder which can be consumed to create a World object.
     #[allow(unknown_lints)]
-    #[allow(inline_always)]
+    #[allow(clippy::inline_always)]
     #[inline(always)]
     pub fn new() -> Self {
-        WorldBuilder { systems: Vec::new() }
+        WorldBuilder {
+            systems: Vec::new(),
+            stages: vec![DEFAULT_STAGE.to_owned()],
+            job_system: Box::new(RayonJobSystem),
+            resources: Map::new(),
+            cloners: HashMap::new(),
+            snapshots: HashMap::new(),
+        }
     }
 
     /// Adds a system to the WorldBuilder, these systems will be permanent in the resulting
-    /// World.
-    pub fn with_system<T: System>(mut self, system: T) -> Self {
-        self.systems.push(Box::new(system));
+    /// World. The system is added to the `"default"` stage, which is processed before every other
+    /// stage.
+    pub fn with_system<T: System>(self, system: T) -> Self {
+        self.with_system_in_stage(DEFAULT_STAGE, system)
+    }
+
+    /// Adds a stage. Stages are processed in the order they were added, a stage that was not
+    /// added through this method is added the first time a system is placed in it. The
+    /// `"default"` stage always exists and is always the first one.
+    pub fn with_stage(mut self, name: &str) -> Self {
+        self.stage_index(name);
+        self
+    }
+
+    /// Adds a system to a stage. Each stage is processed completely (both phases of
+    /// `World::process` and the world's `CommandBuffer`) before the next one starts. Systems of
+    /// the same stage are processed in the order they were added, unless `WorldBuilder::after`
+    /// or `WorldBuilder::before` say otherwise.
+    pub fn with_system_in_stage<T: System>(mut self, stage: &str, system: T) -> Self {
+        let stage = self.stage_index(stage);
+        self.systems.push(SystemEntry {
+            system: Box::new(system),
+            type_id: TypeId::of::<T>(),
+            stage: stage,
+            after: Vec::new(),
+            before: Vec::new(),
+        });
+        self
+    }
+
+    /// Makes the last added system run after the system of type T. Systems of earlier stages
+    /// always run first, so the constraint only matters if both systems are in the same stage.
+    /// # Panics
+    /// Panics if no system was added yet. `WorldBuilder::build` panics if there is no system of
+    /// type T, if T is in a later stage or if the constraints of a stage have a cycle.
+    pub fn after<T: System>(mut self) -> Self {
+        self.systems
+            .last_mut()
+            .expect("WorldBuilder::after must be called after a system is added")
+            .after
+            .push(TypeId::of::<T>());
+        self
+    }
+
+    /// Makes the last added system run before the system of type T. See `WorldBuilder::after`.
+    /// # Panics
+    /// Panics if no system was added yet.
+    pub fn before<T: System>(mut self) -> Self {
+        self.systems
+            .last_mut()
+            .expect("WorldBuilder::before must be called after a system is added")
+            .before
+            .push(TypeId::of::<T>());
+        self
+    }
+
+    /// Sets the `JobSystem` used to run the parallel phase of `World::process`. If no job system
+    /// is set the world uses a `RayonJobSystem`.
+    pub fn with_job_system<T: JobSystem + 'static>(mut self, job_system: T) -> Self {
+        self.job_system = Box::new(job_system);
+        self
+    }
+
+    /// Adds a resource to the WorldBuilder. See `World::insert_resource`.
+    pub fn with_resource<T: Any + Send + Sync>(mut self, resource: T) -> Self {
+        self.resources.insert(resource);
+        self
+    }
+
+    /// Registers T as a cloneable component type. See `World::register_cloneable`.
+    pub fn with_cloneable<T: Any + Clone>(mut self) -> Self {
+        self.cloners.insert(TypeId::of::<T>(), Components::clone_component::<T>);
+        self
+    }
+
+    /// Registers T as a double buffered component type. See `World::register_double_buffered`.
+    pub fn with_double_buffered<T: Any + Clone>(mut self) -> Self {
+        self.snapshots.insert(TypeId::of::<T>(), Components::snapshot::<T>);
         self
     }
 
     /// Consumes the WorldBuilder and return a new World.
+    /// # Panics
+    /// Panics if the ordering constraints can't be satisfied (see `WorldBuilder::after`).
     pub fn build(self) -> World {
-        World {
-            entities: Entities::new(),
-            components: Components::new(),
-            systems: self.systems,
-            to_destroy: Vec::new(),
-        }
+        self.build_world(Entities::new(), Components::new())
     }
 
     /// Consumes the WorldBuilder and return a new World with memory pre-allocated for the Entity
     /// and Component vectors. Use this if you know how many Entities your scene will use.
+    /// # Panics
+    /// Panics if the ordering constraints can't be satisfied (see `WorldBuilder::after`).
     pub fn build_with_capacity(self, capacity: usize) -> World {
+        self.build_world(Entities::with_capacity(capacity),
+                         Components::with_capacity(capacity))
+    }
+
+    fn build_world(self, entities: Entities, components: Components) -> World {
+        let (systems, stages) = sort_systems(self.systems, self.stages.len());
+
         World {
-            entities: Entities::with_capacity(capacity),
-            components: Components::with_capacity(capacity),
-            systems: self.systems,
+            entities: entities,
+            components: components,
+            systems: systems,
+            stages: stages,
             to_destroy: Vec::new(),
+            job_system: self.job_system,
+            commands: Mutex::new(CommandBuffer::new()),
+            hierarchy: Hierarchy::new(),
+            events: Events::new(),
+            resources: self.resources,
+            cloners: self.cloners,
+            snapshots: self.snapshots,
+        }
+    }
+
+    fn stage_index(&mut self, name: &str) -> usize {
+        match self.stages.iter().position(|s| s == name) {
+            Some(index) => index,
+            None => {
+                self.stages.push(name.to_owned());
+                self.stages.len() - 1
+            }
+        }
+    }
+}
+
+const DEFAULT_STAGE: &str = "default";
+
+struct SystemEntry {
+    system: Box<System>,
+    type_id: TypeId,
+    stage: usize,
+    after: Vec<TypeId>,
+    before: Vec<TypeId>,
+}
+
+// Orders the systems by stage and, inside each stage, by their ordering constraints. Systems
+// without constraints between them keep the order they were added in. Returns the sorted systems
+// and the index one past the last system of each stage.
+fn sort_systems(entries: Vec<SystemEntry>, stage_count: usize) -> (Vec<Box<System>>, Vec<usize>) {
+    // The systems that must run before each system.
+    let mut dependencies = vec![Vec::new(); entries.len()];
+    {
+        let find = |type_id: &TypeId| {
+            entries.iter()
+                   .position(|e| e.type_id == *type_id)
+                   .expect("An ordering constraint references a system that is not in the World")
+        };
+        let mut add_dependency = |first: usize, second: usize| {
+            assert!(entries[first].stage <= entries[second].stage,
+                    "A system can't run before a system of an earlier stage");
+            if entries[first].stage == entries[second].stage {
+                dependencies[second].push(first);
+            }
+        };
+
+        for (index, entry) in entries.iter().enumerate() {
+            for other in entry.after.iter().map(&find) {
+                add_dependency(other, index);
+            }
+            for other in entry.before.iter().map(&find) {
+                add_dependency(index, other);
+            }
         }
     }
+
+    let mut order = Vec::with_capacity(entries.len());
+    let mut stages = Vec::with_capacity(stage_count);
+    let mut done = vec![false; entries.len()];
+    for stage in 0..stage_count {
+        while let Some(next) = (0..entries.len()).find(|&i| {
+            !done[i] && entries[i].stage == stage && dependencies[i].iter().all(|&d| done[d])
+        }) {
+            done[next] = true;
+            order.push(next);
+        }
+
+        assert!(!(0..entries.len()).any(|i| !done[i] && entries[i].stage == stage),
+                "The ordering constraints of a stage have a cycle");
+        stages.push(order.len());
+    }
+
+    let mut entries: Vec<_> = entries.into_iter().map(Some).collect();
+    let systems = order.into_iter().map(|i| entries[i].take().unwrap().system).collect();
+    (systems, stages)
 }
 
-fn match_entity_signature(system: &System, components: &Box<[TypeId]>) -> bool {
+fn match_entity_signature(system: &System, components: &[TypeId]) -> bool {
     let signature = system.signature();
     let mut count = 0;
     for s in &*signature {
-        if components.contains(&s) {
-            count = count + 1;
+        if components.contains(s) {
+            count += 1;
         }
     }
 
@@ -111,23 +310,115 @@ impl World {
         self.entities.create_entity()
     }
 
+    /// Reserves an entity through a shared reference, see `Entities::reserve_entity`. The entity
+    /// only becomes valid when the next entity is created or the command that reserved it is
+    /// executed. Used by `CommandBuffer::create_entity`.
+    pub fn reserve_entity(&self) -> Entity {
+        self.entities.reserve_entity()
+    }
+
+    pub(crate) fn create_reserved_entities(&mut self) {
+        self.entities.create_reserved_entities();
+    }
+
     /// Destroy an enttiy. Memory is not released from entity destruction, the next entity
     /// created will reuse the id. Destroyed entities return false when checked through
     /// `World::is_valid`. Entities are only destroyed after the frame is over, calling
     /// `World::is_alive` right after `World::destroy_entity` will still return true.
     /// # Panics
-    /// Panics if the entity is invalid or if it was already sent to be destroyed this frame. This
-    /// is the only function that checks wether an entity is scheduled to be destroyed or not.
+    /// Panics if the entity is invalid or if it was already sent to be destroyed this frame. Use
+    /// `World::is_destroy_scheduled` to check before destroying an entity that other systems
+    /// might destroy too.
     pub fn destroy_entity(&mut self, entity: Entity) {
         assert!(self.entities.is_valid(entity) && !self.to_destroy.contains(&entity));
 
         self.to_destroy.push(entity);
     }
 
+    /// Registers T as a cloneable component type, so `World::clone_entity` copies it.
+    pub fn register_cloneable<T: Any + Clone>(&mut self) {
+        self.cloners.insert(TypeId::of::<T>(), Components::clone_component::<T>);
+    }
+
+    /// Registers T as a double buffered component type. At the end of every `World::process` the
+    /// components of type T are copied to a previous buffer, readable through
+    /// `World::get_previous_component`. Systems can then read the stable state of the last frame
+    /// (to render or interpolate it) while other systems write the current one. Copying happens
+    /// every frame, so only register the types that need it.
+    pub fn register_double_buffered<T: Any + Clone>(&mut self) {
+        self.snapshots.insert(TypeId::of::<T>(), Components::snapshot::<T>);
+        self.components.snapshot::<T>();
+    }
+
+    /// Creates a new entity with a clone of every component of `entity` whose type was
+    /// registered as cloneable (through `World::register_cloneable` or
+    /// `WorldBuilder::with_cloneable`). Other components and the entity's place in the hierarchy
+    /// are not copied. The new entity is applied before it is returned.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn clone_entity(&mut self, entity: Entity) -> Entity {
+        assert!(self.entities.is_valid(entity));
+
+        let clone = self.create_entity();
+        let signature = self.components.generate_signature(entity.id() as usize);
+        for type_id in signature.iter() {
+            if let Some(cloner) = self.cloners.get(type_id) {
+                cloner(&mut self.components, entity.id() as usize, clone.id() as usize);
+            }
+        }
+
+        self.apply(clone);
+        clone
+    }
+
+    /// Destroys an entity and every one of its descendants in the hierarchy. Descendants that
+    /// were already sent to be destroyed this frame are skipped. Entities destroyed through
+    /// `World::destroy_entity` don't take their children with them, the children become roots.
+    /// # Panics
+    /// Panics if the entity is invalid or if it was already sent to be destroyed this frame.
+    pub fn destroy_entity_recursive(&mut self, entity: Entity) {
+        self.destroy_entity(entity);
+
+        for descendant in self.hierarchy.descendants(entity).into_iter().skip(1) {
+            if !self.to_destroy.contains(&descendant) {
+                self.to_destroy.push(descendant);
+            }
+        }
+    }
+
+    /// Returns true if `World::destroy_entity` was called for the entity this frame.
+    pub fn is_destroy_scheduled(&self, entity: Entity) -> bool {
+        self.to_destroy.contains(&entity)
+    }
+
+    /// Deactivates an entity. The entity is removed from every system and skipped by queries, but
+    /// it stays valid and keeps its components, so activating it again is cheap. Used by `Pool` to
+    /// keep entities around until they are needed.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn deactivate(&mut self, entity: Entity) {
+        self.entities.set_active(entity, false);
+        self.apply(entity);
+    }
+
+    /// Activates an entity deactivated through `World::deactivate`, sending it back to the
+    /// systems that accept its signature.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn activate(&mut self, entity: Entity) {
+        self.entities.set_active(entity, true);
+        self.apply(entity);
+    }
+
+    /// Returns true if the entity is valid and was not deactivated.
+    pub fn is_active(&self, entity: Entity) -> bool {
+        self.entities.is_active(entity)
+    }
+
     /// Return the state of an entity, true if the entity is valid, false if the entity was
     /// destroyed or is invalid.
     #[allow(unknown_lints)]
-    #[allow(inline_always)]
+    #[allow(clippy::inline_always)]
     #[inline(always)]
     pub fn is_valid(&self, entity: Entity) -> bool {
         self.entities.is_valid(entity)
@@ -163,6 +454,37 @@ impl World {
         self.components.get_component_mut::<T>(entity.id() as usize)
     }
 
+    /// Returns the component T the entity had at the end of the last call to `World::process`, or
+    /// None if it had no such component or T is not double buffered (see
+    /// `World::register_double_buffered`).
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn get_previous_component<T: Any>(&self, entity: Entity) -> Option<&T> {
+        assert!(self.entities.is_valid(entity));
+        self.components.get_previous::<T>(entity.id() as usize)
+    }
+
+    /// Returns the entities whose component T was changed (added or mutably accessed) during the
+    /// previous frame, that is, before the end of the last call to `World::process`. Only entities
+    /// that are still valid and still have the component are returned.
+    pub fn changed<T: Any>(&self) -> Vec<Entity> {
+        self.components
+            .changed::<T>()
+            .iter()
+            .filter_map(|index| self.entities.get(*index as EntityId))
+            .filter(|entity| self.components.get_component::<T>(entity.id() as usize).is_some())
+            .collect()
+    }
+
+    /// Returns true if the component T of the entity was changed during the previous frame. See
+    /// `World::changed`.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn is_changed<T: Any>(&self, entity: Entity) -> bool {
+        assert!(self.entities.is_valid(entity));
+        self.components.is_changed::<T>(entity.id() as usize)
+    }
+
     /// Removes a component from an entity. Returns the removed component or None if the entity
     /// had no component of type T. Don't forget to apply after removing.
     /// # Panics
@@ -180,6 +502,76 @@ impl World {
         self.components.remove_all_components(entity.id() as usize)
     }
 
+    /// Returns the entities of the world. Iterating over them returns every valid entity.
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    /// Returns the component storage of the world. Useful for systems that need to walk every
+    /// component of a type (through `Components::iter`) instead of going entity by entity. The
+    /// index of each component is the id of the entity that owns it.
+    pub fn components(&self) -> &Components {
+        &self.components
+    }
+
+    /// Returns the mutable component storage of the world. Entity validity is not checked by
+    /// `Components`, and adding or removing components through it still requires a call to
+    /// `World::apply`.
+    pub fn components_mut(&mut self) -> &mut Components {
+        &mut self.components
+    }
+
+    /// Returns an iterator over every active entity that has all the components requested by `Q`,
+    /// together with those components. Only read only queries (`&T` and tuples of them) can be
+    /// used, so this is safe to call from `System::process`. The cost of the iteration depends on
+    /// the number of components of the rarest type in `Q`, not on the number of entities.
+    pub fn query<'a, Q: ReadQuery<'a>>(&'a self) -> QueryIter<'a, Q> {
+        let components = &self.components as *const Components as *mut Components;
+        // Read only queries only create shared references to the components.
+        unsafe { QueryIter::new(&self.entities, components) }
+    }
+
+    /// Returns an iterator over every active entity that has all the components requested by `Q`,
+    /// together with those components. `Q` can mix `&T` and `&mut T`.
+    /// # Panics
+    /// Panics if `Q` requests the same component type more than once.
+    pub fn query_mut<'a, Q: Query<'a>>(&'a mut self) -> QueryIter<'a, Q> {
+        check_unique_types::<Q>();
+        unsafe { QueryIter::new(&self.entities, &mut self.components) }
+    }
+
+    /// Inserts a resource, global state that doesn't belong to any entity (the frame time, the
+    /// input state, etc.). Only one resource of each type can exist, inserting a resource of a
+    /// type that is already in the world replaces it and returns the old one.
+    pub fn insert_resource<T: Any + Send + Sync>(&mut self, resource: T) -> Option<T> {
+        self.resources.insert(resource)
+    }
+
+    /// Returns a reference to the resource of type T, or None if there is no such resource.
+    pub fn get_resource<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.resources.get::<T>()
+    }
+
+    /// Returns a mutable reference to the resource of type T, or None if there is no such
+    /// resource.
+    pub fn get_resource_mut<T: Any + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut::<T>()
+    }
+
+    /// Removes the resource of type T from the world. Returns the removed resource or None if
+    /// there was no resource of type T.
+    pub fn remove_resource<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.resources.remove::<T>()
+    }
+
+    /// Releases the memory kept for the components of destroyed entities. Worlds that create and
+    /// destroy many entities over time should call this every now and then (between levels, for
+    /// example). Entity ids are kept, so destroyed entities are still recognized as invalid when
+    /// their id is reused.
+    pub fn compact(&mut self) {
+        self.components.compact();
+    }
+
     /// Returns a reference to a system. Returns None if no system of type T can be found.
     pub fn get_system_mut<T: System>(&mut self) -> Option<&mut T> {
         self.systems.iter_mut().filter_map(|s| s.downcast_mut::<T>()).next()
@@ -190,15 +582,89 @@ impl World {
         self.systems.iter().filter_map(|s| s.downcast_ref::<T>()).next()
     }
 
+    /// Returns the `JobSystem` the world uses to process the systems. Systems can use it during
+    /// `System::process` to split their own read only work.
+    pub fn job_system(&self) -> &JobSystem {
+        &*self.job_system
+    }
+
+    /// Makes `child` a child of `parent`, detaching it from its previous parent.
+    /// # Panics
+    /// Panics if either entity is invalid or if `parent` is `child` or one of its descendants.
+    pub fn attach(&mut self, child: Entity, parent: Entity) {
+        assert!(self.entities.is_valid(child) && self.entities.is_valid(parent));
+        self.hierarchy.attach(child, parent)
+    }
+
+    /// Removes `child` from its parent, making it a root.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn detach(&mut self, child: Entity) {
+        assert!(self.entities.is_valid(child));
+        self.hierarchy.detach(child)
+    }
+
+    /// Returns the parent of the entity, or None if the entity is a root.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        assert!(self.entities.is_valid(entity));
+        self.hierarchy.parent(entity)
+    }
+
+    /// Returns the children of the entity in the order they were attached.
+    /// # Panics
+    /// Panics if the entity is invalid.
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        assert!(self.entities.is_valid(entity));
+        self.hierarchy.children(entity)
+    }
+
+    /// Returns the parent/child relations between the entities of the world.
+    pub fn hierarchy(&self) -> &Hierarchy {
+        &self.hierarchy
+    }
+
+    /// Emits an event. Events can be emitted at any time, including during `System::process`,
+    /// and become readable through `World::read_events` when the current call to `World::process`
+    /// ends (or the next one, if the world is not being processed). They stay readable until the
+    /// end of the following call to `World::process`.
+    pub fn emit<T: Any + Send + Sync>(&self, event: T) {
+        self.events.emit(event)
+    }
+
+    /// Returns the events of type `T` emitted during the previous frame, in the order they were
+    /// emitted. The order of events emitted by different systems during the parallel phase of
+    /// `World::process` is not specified.
+    pub fn read_events<T: Any + Send + Sync>(&self) -> &[T] {
+        self.events.read::<T>()
+    }
+
+    /// Returns the world's `CommandBuffer`. Commands recorded in it (even from `System::process`,
+    /// where only a shared reference to the world is available) are executed by `World::process`
+    /// after the system callbacks of each stage ran. Commands recorded while the buffer is being
+    /// flushed are executed after the next stage, or during the next `World::process` if the
+    /// buffer was flushed after the last stage.
+    ///
+    /// The buffer is behind a `Mutex`, drop the returned guard before calling `World::commands`
+    /// again on the same thread. Locking it twice from one thread may deadlock or panic, the
+    /// behavior is not specified.
+    pub fn commands(&self) -> MutexGuard<'_, CommandBuffer> {
+        self.commands.lock().unwrap()
+    }
+
     /// Applies the changes made to an entity, refreshing the entity within the systems. This
     /// should be called after adding or removing components from an entity. Entity destruction
     /// doesn't have to be followed by an apply call.
     pub fn apply(&mut self, entity: Entity) {
         assert!(self.entities.is_valid(entity));
 
+        // Inactive entities are removed from every system.
+        let active = self.entities.is_active(entity);
         let World { ref mut systems, ref mut components, .. } = *self;
         for system in systems.iter_mut() {
-            if match_entity_signature(&**system,
+            if active &&
+               match_entity_signature(&**system,
                                       &components.generate_signature(entity.id() as usize)) {
                 if !system.has_entity(entity) {
                     system.on_entity_added(entity);
@@ -209,23 +675,51 @@ impl World {
         }
     }
 
-    /// Processes every system. The processing runs in two phases, a read only parallel phase
-    /// and a read-write synchronized phase.
+    /// Processes every system, stage by stage. The processing of each stage runs in two phases, a
+    /// read only parallel phase (run through the world's `JobSystem`) and a read-write
+    /// synchronized phase. The commands recorded in the world's `CommandBuffer` are executed after
+    /// the synchronized phase of each stage, and the events emitted and components changed during
+    /// the frame become readable at the end, when the double buffered components are copied too.
     pub fn process(&mut self) {
-        use rayon::par_iter::*;
+        let mut start = 0;
+        for stage in 0..self.stages.len() {
+            let end = self.stages[stage];
+            self.process_stage(start, end);
+            start = end;
+        }
+
+        self.destroy_scheduled_entities();
+        self.events.swap();
+        self.components.swap_changes();
+        for snapshot in self.snapshots.values() {
+            snapshot(&mut self.components);
+        }
+    }
 
-        let mut callbacks = Vec::with_capacity(self.systems.len());
+    fn process_stage(&mut self, start: usize, end: usize) {
+        let mut callbacks = Vec::with_capacity(end - start);
+        for _ in start..end {
+            callbacks.push(Mutex::new(None));
+        }
 
-        self.systems // TODO: make sure this is being run asynchronously
-            .par_iter()
-            .map(|s| s.process(self))
-            .collect_into(&mut callbacks);
+        {
+            let world = &*self;
+            world.job_system.parallel_for(end - start, &|index| {
+                let callback = world.systems[start + index].process(world);
+                *callbacks[index].lock().unwrap() = Some(callback);
+            });
+        }
 
-        for callback in &mut callbacks {
+        for callback in callbacks {
+            let mut callback = callback.into_inner()
+                                       .unwrap()
+                                       .expect("System was not processed. This should never \
+                                                happen");
             (*callback)(self);
         }
 
-        self.destroy_scheduled_entities();
+        let mut commands = mem::replace(&mut *self.commands(), CommandBuffer::new());
+        commands.flush(self);
     }
 
     fn destroy_scheduled_entities(&mut self) {
@@ -233,6 +727,7 @@ impl World {
         for entity in to_destroy {
             self.remove_all_components(entity);
             self.apply(entity);
+            self.hierarchy.remove(entity);
             self.entities.destroy_entity(entity);
         }
         self.to_destroy.clear();
@@ -241,6 +736,8 @@ impl World {
 
 impl Drop for World {
     fn drop(&mut self) {
+        // Every entity is destroyed, including the ones that were already scheduled.
+        self.to_destroy.clear();
         for entity in &self.entities {
             self.to_destroy.push(entity);
         }
@@ -257,9 +754,9 @@ mod test {
     use std::any::TypeId;
     use std;
 
-    #[derive(Default, PartialEq, Debug)]
+    #[derive(Default, PartialEq, Debug, Clone)]
     struct PositionComponent(f32, f32, f32);
-    #[derive(Default)]
+    #[derive(Default, PartialEq, Debug, Clone)]
     struct VelocityComponent(f32, f32, f32);
 
     #[derive(Default)]
@@ -300,7 +797,7 @@ mod test {
             if !w.get_system::<VelocitySystem>().unwrap().marker {
                 assert_eq!(w.get_system::<SpatialSystem>().unwrap().marker, true);
                 w.get_system_mut::<VelocitySystem>().unwrap().marker = true;
-                assert_eq!(v1, v1);
+                assert_eq!(v1, PositionComponent(0.0, 0.0, 0.0));
             }
         })
     });
@@ -367,4 +864,195 @@ mod test {
         w.process();
     }
 
+    #[derive(Default)]
+    struct InputSystem {
+        entities: Vec<Entity>,
+    }
+    impl_system!(InputSystem, (PositionComponent), {
+        Box::new(|w: &mut World| w.get_resource_mut::<Vec<&str>>().unwrap().push("input"))
+    });
+
+    #[derive(Default)]
+    struct PhysicsSystem {
+        entities: Vec<Entity>,
+    }
+    impl_system!(PhysicsSystem, (PositionComponent), {
+        Box::new(|w: &mut World| w.get_resource_mut::<Vec<&str>>().unwrap().push("physics"))
+    });
+
+    #[derive(Default)]
+    struct RenderSystem {
+        entities: Vec<Entity>,
+    }
+    impl_system!(RenderSystem, (PositionComponent), {
+        Box::new(|w: &mut World| w.get_resource_mut::<Vec<&str>>().unwrap().push("render"))
+    });
+
+    #[test]
+    fn stages() {
+        let mut w = WorldBuilder::new()
+                        .with_resource(Vec::<&str>::new())
+                        .with_stage("update")
+                        .with_system_in_stage("render", RenderSystem::default())
+                        .with_system_in_stage("update", PhysicsSystem::default())
+                        .with_system_in_stage("update", InputSystem::default())
+                        .before::<PhysicsSystem>()
+                        .build();
+        w.process();
+        assert_eq!(w.get_resource::<Vec<&str>>().unwrap(),
+                   &vec!["input", "physics", "render"]);
+
+        let mut w = WorldBuilder::new()
+                        .with_resource(Vec::<&str>::new())
+                        .with_system(RenderSystem::default())
+                        .after::<PhysicsSystem>()
+                        .with_system(PhysicsSystem::default())
+                        .with_system(InputSystem::default())
+                        .build();
+        // Systems run as soon as their constraints allow it.
+        w.process();
+        assert_eq!(w.get_resource::<Vec<&str>>().unwrap(),
+                   &vec!["physics", "render", "input"]);
+    }
+
+    #[test]
+    fn default_stage_runs_first() {
+        let mut w = WorldBuilder::new()
+                        .with_resource(Vec::<&str>::new())
+                        .with_stage("render")
+                        .with_system_in_stage("render", RenderSystem::default())
+                        .with_system(PhysicsSystem::default())
+                        .build();
+        w.process();
+        assert_eq!(w.get_resource::<Vec<&str>>().unwrap(), &vec!["physics", "render"]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ordering_cycles() {
+        WorldBuilder::new()
+            .with_system(PhysicsSystem::default())
+            .after::<RenderSystem>()
+            .with_system(RenderSystem::default())
+            .after::<PhysicsSystem>()
+            .build();
+    }
+
+    #[test]
+    #[should_panic]
+    fn ordering_across_stages() {
+        WorldBuilder::new()
+            .with_system_in_stage("update", PhysicsSystem::default())
+            .with_system_in_stage("render", RenderSystem::default())
+            .before::<PhysicsSystem>()
+            .build();
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct FrameCount(u32);
+
+    #[test]
+    fn resources() {
+        let mut w = WorldBuilder::new().with_resource(FrameCount(0)).build();
+        assert_eq!(w.get_resource::<FrameCount>(), Some(&FrameCount(0)));
+        assert_eq!(w.get_resource::<u32>(), None);
+
+        w.get_resource_mut::<FrameCount>().unwrap().0 += 1;
+        assert_eq!(w.insert_resource(FrameCount(10)), Some(FrameCount(1)));
+        assert_eq!(w.insert_resource(5u32), None);
+
+        assert_eq!(w.remove_resource::<FrameCount>(), Some(FrameCount(10)));
+        assert_eq!(w.get_resource::<FrameCount>(), None);
+        assert_eq!(w.get_resource::<u32>(), Some(&5));
+    }
+
+    #[test]
+    fn change_detection() {
+        let mut w = WorldBuilder::new().build();
+        let e1 = w.create_entity();
+        w.add_component(e1, PositionComponent(0.0, 0.0, 0.0));
+        let e2 = w.create_entity();
+        w.add_component(e2, PositionComponent(0.0, 0.0, 0.0));
+        assert!(w.changed::<PositionComponent>().is_empty());
+
+        w.process();
+        assert_eq!(w.changed::<PositionComponent>(), vec![e1, e2]);
+
+        w.get_component_mut::<PositionComponent>(e2).unwrap().0 = 1.0;
+        w.process();
+        assert_eq!(w.changed::<PositionComponent>(), vec![e2]);
+        assert!(w.is_changed::<PositionComponent>(e2));
+        assert!(!w.is_changed::<PositionComponent>(e1));
+
+        w.get_component_mut::<PositionComponent>(e1).unwrap().0 = 1.0;
+        w.destroy_entity(e1);
+        w.process();
+        assert!(w.changed::<PositionComponent>().is_empty());
+    }
+
+    #[test]
+    fn cloning() {
+        let mut w = WorldBuilder::new().with_cloneable::<PositionComponent>().build();
+        let e = w.create_entity();
+        w.add_component(e, PositionComponent(1.0, 2.0, 3.0));
+        w.add_component(e, VelocityComponent(1.0, 1.0, 1.0));
+        w.apply(e);
+
+        let clone = w.clone_entity(e);
+        assert!(clone != e);
+        assert_eq!(w.get_component::<PositionComponent>(clone),
+                   Some(&PositionComponent(1.0, 2.0, 3.0)));
+        assert!(w.get_component::<VelocityComponent>(clone).is_none());
+
+        w.get_component_mut::<PositionComponent>(clone).unwrap().0 = 5.0;
+        assert_eq!(w.get_component::<PositionComponent>(e).unwrap().0, 1.0);
+    }
+
+    #[test]
+    fn double_buffering() {
+        let mut w = WorldBuilder::new().with_double_buffered::<PositionComponent>().build();
+        let e = w.create_entity();
+        w.add_component(e, PositionComponent(1.0, 0.0, 0.0));
+        w.add_component(e, VelocityComponent(1.0, 0.0, 0.0));
+        w.apply(e);
+        assert_eq!(w.get_previous_component::<PositionComponent>(e), None);
+
+        w.process();
+        w.get_component_mut::<PositionComponent>(e).unwrap().0 = 2.0;
+        assert_eq!(w.get_previous_component::<PositionComponent>(e),
+                   Some(&PositionComponent(1.0, 0.0, 0.0)));
+        assert_eq!(w.get_previous_component::<VelocityComponent>(e), None);
+
+        w.process();
+        assert_eq!(w.get_previous_component::<PositionComponent>(e),
+                   Some(&PositionComponent(2.0, 0.0, 0.0)));
+
+        // Registering a type later copies its components right away.
+        w.register_double_buffered::<VelocityComponent>();
+        assert_eq!(w.get_previous_component::<VelocityComponent>(e),
+                   Some(&VelocityComponent(1.0, 0.0, 0.0)));
+
+        w.remove_component::<PositionComponent>(e);
+        w.apply(e);
+        assert!(w.get_previous_component::<PositionComponent>(e).is_some());
+        w.process();
+        assert_eq!(w.get_previous_component::<PositionComponent>(e), None);
+        assert_eq!(w.components().iter_previous::<VelocityComponent>().count(), 1);
+    }
+
+    #[test]
+    fn serial_job_system() {
+        use super::super::SerialJobSystem;
+
+        let mut w = WorldBuilder::new()
+                        .with_system(SpatialSystem::default())
+                        .with_system(VelocitySystem::default())
+                        .with_job_system(SerialJobSystem)
+                        .build();
+
+        w.process();
+
+        assert_eq!(w.get_system::<SpatialSystem>().unwrap().marker, true);
+        assert_eq!(w.get_system::<VelocitySystem>().unwrap().marker, true);
+    }
 }
@@ -0,0 +1,154 @@
+//! A module for the `Curve` type. Curves are a list of keyframes interpolated with cubic hermite
+//! splines, they are useful to describe how a value changes over time (easing, falloff, etc.)
+//! without writing the function in code.
+
+use std::cmp::Ordering;
+
+/// A point in a `Curve`. The tangents are the slope of the curve when arriving at the keyframe
+/// (`in_tangent`) and when leaving it (`out_tangent`).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    ///
+    pub time: f32,
+    ///
+    pub value: f32,
+    ///
+    pub in_tangent: f32,
+    ///
+    pub out_tangent: f32,
+}
+
+impl Keyframe {
+    /// Returns a new instance of a keyframe with the specified values.
+    pub fn new(time: f32, value: f32, in_tangent: f32, out_tangent: f32) -> Self {
+        Keyframe {
+            time: time,
+            value: value,
+            in_tangent: in_tangent,
+            out_tangent: out_tangent,
+        }
+    }
+
+    /// Returns a keyframe with flat tangents.
+    pub fn flat(time: f32, value: f32) -> Self {
+        Keyframe::new(time, value, 0.0, 0.0)
+    }
+}
+
+/// A list of keyframes sorted by time.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Curve {
+    keys: Vec<Keyframe>,
+}
+
+impl Curve {
+    /// Returns an empty curve. Empty curves always evaluate to zero.
+    pub fn new() -> Self {
+        Curve { keys: Vec::new() }
+    }
+
+    /// Returns a curve with the keyframes. The keyframes don't need to be sorted.
+    pub fn with_keys(keys: Vec<Keyframe>) -> Self {
+        let mut curve = Curve { keys: keys };
+        curve.keys.sort_by(compare_time);
+        curve
+    }
+
+    /// Returns a curve that goes in a straight line from `(0, from)` to `(1, to)`.
+    pub fn linear(from: f32, to: f32) -> Self {
+        let slope = to - from;
+        Curve::with_keys(vec![Keyframe::new(0.0, from, slope, slope),
+                              Keyframe::new(1.0, to, slope, slope)])
+    }
+
+    /// Adds a keyframe to the curve, keeping the keyframes sorted. If there is already a keyframe
+    /// with the same time it is replaced.
+    pub fn add_key(&mut self, key: Keyframe) {
+        match self.keys.binary_search_by(|k| compare_time(k, &key)) {
+            Ok(index) => self.keys[index] = key,
+            Err(index) => self.keys.insert(index, key),
+        }
+    }
+
+    /// Returns the keyframes of the curve sorted by time.
+    pub fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    /// Returns the value of the curve at the time `t`. Times before the first keyframe return the
+    /// value of the first keyframe and times after the last keyframe return the value of the last
+    /// keyframe.
+    pub fn evaluate(&self, t: f32) -> f32 {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        if t <= first.time {
+            return first.value;
+        }
+        if t >= last.time {
+            return last.value;
+        }
+
+        // The index of the first keyframe after t. It can't be 0 or keys.len() since t is
+        // between the first and last keyframes.
+        let next = match self.keys.binary_search_by(|k| compare_time(k, &Keyframe::flat(t, 0.0))) {
+            Ok(index) => return self.keys[index].value,
+            Err(index) => index,
+        };
+
+        let k0 = self.keys[next - 1];
+        let k1 = self.keys[next];
+        let dt = k1.time - k0.time;
+        let s = (t - k0.time) / dt;
+        let s2 = s * s;
+        let s3 = s2 * s;
+
+        let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+        let h10 = s3 - 2.0 * s2 + s;
+        let h01 = -2.0 * s3 + 3.0 * s2;
+        let h11 = s3 - s2;
+
+        h00 * k0.value + h10 * dt * k0.out_tangent + h01 * k1.value + h11 * dt * k1.in_tangent
+    }
+}
+
+fn compare_time(a: &Keyframe, b: &Keyframe) -> Ordering {
+    a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Curve, Keyframe};
+
+    #[test]
+    fn evaluation() {
+        assert_eq!(Curve::new().evaluate(0.5), 0.0);
+
+        let curve = Curve::linear(0.0, 10.0);
+        assert_eq!(curve.evaluate(-1.0), 0.0);
+        assert_eq!(curve.evaluate(0.5), 5.0);
+        assert_eq!(curve.evaluate(0.25), 2.5);
+        assert_eq!(curve.evaluate(2.0), 10.0);
+
+        // Flat tangents make a smooth step.
+        let curve = Curve::with_keys(vec![Keyframe::flat(2.0, 1.0), Keyframe::flat(0.0, 0.0)]);
+        assert_eq!(curve.evaluate(1.0), 0.5);
+        assert!(curve.evaluate(0.5) < 0.25);
+        assert!(curve.evaluate(1.5) > 0.75);
+    }
+
+    #[test]
+    fn keys() {
+        let mut curve = Curve::new();
+        curve.add_key(Keyframe::flat(1.0, 1.0));
+        curve.add_key(Keyframe::flat(0.0, 0.0));
+        curve.add_key(Keyframe::flat(0.5, 2.0));
+        curve.add_key(Keyframe::flat(0.5, 3.0));
+
+        let times: Vec<f32> = curve.keys().iter().map(|k| k.time).collect();
+        assert_eq!(times, vec![0.0, 0.5, 1.0]);
+        assert_eq!(curve.evaluate(0.5), 3.0);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Vector3, Vector4, Matrix4, normalize, cross, dot};
+use super::{Vector3, Vector4, Matrix4, Quaternion, normalize, cross, dot};
 use num::traits::{Zero, One};
 
 /// Returns a look at matrix from the supplied parameters. Eye is the camera position, center is
@@ -170,3 +170,107 @@ pub fn orthonormalize(x: &mut Vector3<f32>, y: &mut Vector3<f32>) {
     *x = normalize(*x);
     *y = normalize(*x - *y * dot(*y, *x));
 }
+
+/// Snaps a value to the closest multiple of `increment`. Values halfway between two multiples are
+/// rounded away from zero. If `increment` is zero, negative or NaN the value is returned unchanged.
+pub fn snap(value: f32, increment: f32) -> f32 {
+    if increment > 0.0 {
+        (value / increment).round() * increment
+    } else {
+        value
+    }
+}
+
+/// Snaps a position to the closest point of a grid. Each axis of `cell_size` is the size of a
+/// grid cell in that axis, axis with a size of zero (or negative, or NaN) are not snapped.
+pub fn snap_to_grid(position: Vector3<f32>, cell_size: Vector3<f32>) -> Vector3<f32> {
+    Vector3::new(snap(position.x, cell_size.x),
+                 snap(position.y, cell_size.y),
+                 snap(position.z, cell_size.z))
+}
+
+/// Snaps every euler angle of a rotation to a multiple of `increment`. The increment should be
+/// supplied in radians.
+///
+/// The euler angles of a rotation close to gimbal lock (a yaw near ±90°) are not unique, so such
+/// rotations may snap to an orientation that is not the closest one. Rotations that must stay
+/// exact around those angles should be snapped by the caller with `snap` on each angle.
+pub fn snap_rotation(orientation: Quaternion, increment: f32) -> Quaternion {
+    let euler = euler_angles(orientation);
+    Quaternion::from_euler(Vector3::new(snap(euler.x, increment),
+                                        snap(euler.y, increment),
+                                        snap(euler.z, increment)))
+}
+
+// The inverse of `Quaternion::from_euler`. `Quaternion::to_euler` can't be used here since glm's
+// `atan2` swaps its arguments.
+fn euler_angles(q: Quaternion) -> Vector3<f32> {
+    let pitch = (2.0 * (q.y * q.z + q.w * q.x))
+                    .atan2(q.w * q.w - q.x * q.x - q.y * q.y + q.z * q.z);
+    let yaw = (-2.0 * (q.x * q.z - q.w * q.y)).clamp(-1.0, 1.0).asin();
+    let roll = (2.0 * (q.x * q.y + q.w * q.z)).atan2(q.w * q.w + q.x * q.x - q.y * q.y - q.z * q.z);
+    Vector3::new(pitch, yaw, roll)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{snap, snap_rotation, snap_to_grid};
+    use super::super::{Quaternion, Vector3};
+    use num::traits::Zero;
+    use std::f32::consts::PI;
+
+    // q and -q are the same rotation, so the sign is ignored.
+    fn assert_rotation_eq(a: Quaternion, b: Quaternion) {
+        let sign = if a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w < 0.0 { -1.0 } else { 1.0 };
+        assert!((a.x - sign * b.x).abs() < 1e-5 && (a.y - sign * b.y).abs() < 1e-5 &&
+                (a.z - sign * b.z).abs() < 1e-5 && (a.w - sign * b.w).abs() < 1e-5,
+                "{:?} != {:?}",
+                a,
+                b);
+    }
+
+    #[test]
+    fn snapping() {
+        assert_eq!(snap(2.6, 1.0), 3.0);
+        assert_eq!(snap(-2.6, 1.0), -3.0);
+        assert_eq!(snap(1.1, 0.25), 1.0);
+        assert_eq!(snap(7.0, 5.0), 5.0);
+
+        // Halfway values are rounded away from zero.
+        assert_eq!(snap(0.5, 1.0), 1.0);
+        assert_eq!(snap(-0.5, 1.0), -1.0);
+        assert_eq!(snap(0.75, 0.5), 1.0);
+        assert_eq!(snap(-0.25, 0.5), -0.5);
+
+        // Increments that are zero, negative or NaN leave the value unchanged.
+        assert_eq!(snap(2.6, 0.0), 2.6);
+        assert_eq!(snap(2.6, -1.0), 2.6);
+        assert_eq!(snap(2.6, f32::NAN), 2.6);
+        assert!(snap(f32::NAN, 1.0).is_nan());
+    }
+
+    #[test]
+    fn grid_snapping() {
+        let position = Vector3::new(1.3, -2.6, 7.2);
+        assert_eq!(snap_to_grid(position, Vector3::new(0.5, 1.0, 0.0)),
+                   Vector3::new(1.5, -3.0, 7.2));
+        assert_eq!(snap_to_grid(position, Vector3::new(-1.0, f32::NAN, 0.0)), position);
+        assert_eq!(snap_to_grid(Vector3::new(0.5, -0.5, 0.25), Vector3::new(1.0, 1.0, 0.5)),
+                   Vector3::new(1.0, -1.0, 0.5));
+    }
+
+    #[test]
+    fn rotation_snapping() {
+        let degrees = PI / 180.0;
+        assert_rotation_eq(snap_rotation(Quaternion::zero(), 45.0 * degrees), Quaternion::zero());
+
+        let q = Quaternion::from_euler(Vector3::new(50.0, 20.0, -100.0) * degrees);
+        assert_rotation_eq(snap_rotation(q, 45.0 * degrees),
+                           Quaternion::from_euler(Vector3::new(45.0, 0.0, -90.0) * degrees));
+
+        // Increments that are zero, negative or NaN keep the rotation.
+        assert_rotation_eq(snap_rotation(q, 0.0), q);
+        assert_rotation_eq(snap_rotation(q, -1.0), q);
+        assert_rotation_eq(snap_rotation(q, f32::NAN), q);
+    }
+}
@@ -7,10 +7,12 @@ extern crate glm;
 extern crate num;
 
 pub mod aabb;
+pub mod curve;
 mod quaternion;
 mod extensions;
 
 pub use glm::*;
 pub use aabb::Aabb;
+pub use curve::{Curve, Keyframe};
 pub use quaternion::*;
 pub use extensions::*;